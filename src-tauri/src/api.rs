@@ -11,8 +11,8 @@ use tokio::sync::Mutex;
 use self::{
     requests::{make_request, BungieRequest, BungieResponseError},
     responses::{
-        ActivityInfo, BungieProfile, CharacterActivityHistory, ProfileCurrentActivities,
-        ProfileInfo,
+        ActivityInfo, AggregateActivityStats, BungieProfile, CharacterActivityHistory,
+        ProfileCharacterMilestones, ProfileCharacters, ProfileCurrentActivities, ProfileInfo,
     },
 };
 use crate::config::profiles::Profile;
@@ -84,7 +84,7 @@ impl Source<Profile, ProfileInfo> for ProfileInfoSource {
         let res_val = make_request(BungieRequest::GetProfile {
             membership_type: profile.account_platform,
             membership_id: &profile.account_id,
-            component: 100,
+            components: &[100],
         })
         .await
         .map_err(|e| ApiError::ResponseError(e))?;
@@ -97,6 +97,38 @@ impl Source<Profile, ProfileInfo> for ProfileInfoSource {
     }
 }
 
+#[derive(Default)]
+pub struct CharacterInfoSource {
+    cache: HashMap<Profile, ProfileCharacters>,
+}
+
+impl CharacterInfoSource {
+    // Light level and last played time change as the profile is played,
+    // so the cached characters are dropped whenever a new activity starts
+    pub fn invalidate(&mut self, profile: &Profile) {
+        self.cache.remove(profile);
+    }
+}
+
+#[async_trait]
+impl Source<Profile, ProfileCharacters> for CharacterInfoSource {
+    async fn get_value(profile: Profile) -> Result<ProfileCharacters, ApiError> {
+        let res_val = make_request(BungieRequest::GetProfile {
+            membership_type: profile.account_platform,
+            membership_id: &profile.account_id,
+            components: &[200],
+        })
+        .await
+        .map_err(|e| ApiError::ResponseError(e))?;
+
+        serde_json::from_value(res_val).map_err(|e| ApiError::ResponseDeserializeError(e))
+    }
+
+    fn cache(&mut self) -> &mut HashMap<Profile, ProfileCharacters> {
+        &mut self.cache
+    }
+}
+
 #[derive(Default)]
 pub struct ActivityInfoSource {
     cache: HashMap<usize, ActivityInfo>,
@@ -120,6 +152,7 @@ impl Source<usize, ActivityInfo> for ActivityInfoSource {
 #[derive(Default)]
 pub struct Api {
     pub profile_info_source: Mutex<ProfileInfoSource>,
+    pub character_info_source: Mutex<CharacterInfoSource>,
     pub activity_info_source: Mutex<ActivityInfoSource>,
 }
 
@@ -144,7 +177,21 @@ impl Api {
         let res_val = make_request(BungieRequest::GetProfile {
             membership_type: profile.account_platform,
             membership_id: &profile.account_id,
-            component: 204,
+            components: &[204, 1000],
+        })
+        .await
+        .map_err(|e| ApiError::ResponseError(e))?;
+
+        serde_json::from_value(res_val).map_err(|e| ApiError::ResponseDeserializeError(e))
+    }
+
+    pub async fn get_profile_milestones(
+        profile: &Profile,
+    ) -> Result<ProfileCharacterMilestones, ApiError> {
+        let res_val = make_request(BungieRequest::GetProfile {
+            membership_type: profile.account_platform,
+            membership_id: &profile.account_id,
+            components: &[202],
         })
         .await
         .map_err(|e| ApiError::ResponseError(e))?;
@@ -168,4 +215,19 @@ impl Api {
 
         serde_json::from_value(res_val).map_err(|e| ApiError::ResponseDeserializeError(e))
     }
+
+    pub async fn get_aggregate_activity_stats(
+        profile: &Profile,
+        character_id: &String,
+    ) -> Result<AggregateActivityStats, ApiError> {
+        let res_val = make_request(BungieRequest::GetAggregateActivityStats {
+            membership_type: profile.account_platform,
+            membership_id: &profile.account_id,
+            character_id: character_id,
+        })
+        .await
+        .map_err(|e| ApiError::ResponseError(e))?;
+
+        serde_json::from_value(res_val).map_err(|e| ApiError::ResponseDeserializeError(e))
+    }
 }
@@ -3,6 +3,7 @@ use std::{
     fmt::{Display, Formatter},
 };
 
+use itertools::Itertools;
 use reqwest::{Client, Method, RequestBuilder};
 use serde::Deserialize;
 use serde_json::{json, Value};
@@ -17,7 +18,7 @@ pub enum BungieRequest<'a> {
     GetProfile {
         membership_type: usize,
         membership_id: &'a str,
-        component: usize,
+        components: &'a [usize],
     },
     GetActivityHistory {
         membership_type: usize,
@@ -28,6 +29,11 @@ pub enum BungieRequest<'a> {
     GetDestinyActivityDefinition {
         activity_hash: usize,
     },
+    GetAggregateActivityStats {
+        membership_type: usize,
+        membership_id: &'a str,
+        character_id: &'a str,
+    },
 }
 
 #[derive(Deserialize)]
@@ -95,13 +101,16 @@ pub async fn make_request(req: BungieRequest<'_>) -> Result<Value, BungieRespons
             "/Destiny2/SearchDestinyPlayerByBungieName/All",
             Method::POST,
         ).body(json!({"displayName": display_name, "displayNameCode": display_name_code}).to_string()),
-        BungieRequest::GetProfile { membership_type, membership_id, component } => {
-            api_request(&format!("/Destiny2/{membership_type}/Profile/{membership_id}?components={component}"), Method::GET)
+        BungieRequest::GetProfile { membership_type, membership_id, components } => {
+            api_request(&format!("/Destiny2/{membership_type}/Profile/{membership_id}?components={}", components.iter().join(",")), Method::GET)
         }
         BungieRequest::GetActivityHistory { membership_type, membership_id, character_id, page } => {
             api_request(&format!("/Destiny2/{membership_type}/Account/{membership_id}/Character/{character_id}/Stats/Activities?mode=7&count=25&page={page}"), Method::GET)
         }
         BungieRequest::GetDestinyActivityDefinition { activity_hash } => api_request(&format!("/Destiny2/Manifest/DestinyActivityDefinition/{activity_hash}"), Method::GET),
+        BungieRequest::GetAggregateActivityStats { membership_type, membership_id, character_id } => {
+            api_request(&format!("/Destiny2/{membership_type}/Account/{membership_id}/Character/{character_id}/Stats/AggregateActivityStats/"), Method::GET)
+        }
     };
 
     let resp = builder
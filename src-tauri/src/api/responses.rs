@@ -70,10 +70,90 @@ impl<'de> Deserialize<'de> for ProfileInfo {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct ProfileCharacters {
+    pub privacy: usize,
+    pub characters: Option<HashMap<String, CharacterInfo>>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct CharacterInfo {
+    pub character_id: String,
+    pub class_type: usize,
+    pub race_type: usize,
+    pub light: usize,
+    pub emblem_path: String,
+    pub date_last_played: DateTime<Utc>,
+}
+
+impl<'de> Deserialize<'de> for ProfileCharacters {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Profile {
+            characters: _Characters,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Characters {
+            data: Option<HashMap<String, _Character>>,
+            privacy: usize,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Character {
+            character_id: String,
+            class_type: usize,
+            race_type: usize,
+            light: usize,
+            emblem_path: String,
+            date_last_played: DateTime<Utc>,
+        }
+
+        let profile = _Profile::deserialize(deserializer)?;
+        Ok(Self {
+            privacy: profile.characters.privacy,
+            characters: profile.characters.data.map(|d| {
+                d.into_iter()
+                    .map(|e| {
+                        (
+                            e.0,
+                            CharacterInfo {
+                                character_id: e.1.character_id,
+                                class_type: e.1.class_type,
+                                race_type: e.1.race_type,
+                                light: e.1.light,
+                                emblem_path: e.1.emblem_path,
+                                date_last_played: e.1.date_last_played,
+                            },
+                        )
+                    })
+                    .collect()
+            }),
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct ProfileCurrentActivities {
     pub privacy: usize,
     pub activities: Option<HashMap<String, LatestCharacterActivity>>,
+    pub online: bool,
+    pub fireteam: Vec<FireteamMember>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FireteamMember {
+    pub membership_id: String,
+    pub display_name: String,
+    pub emblem_hash: usize,
 }
 
 #[derive(Debug, Serialize, Clone, PartialEq, Eq)]
@@ -105,6 +185,29 @@ impl<'de> Deserialize<'de> for ProfileCurrentActivities {
         #[serde(rename_all = "camelCase")]
         struct _Profile {
             character_activities: _CurrentActivities,
+            profile_transitory_data: Option<_TransitoryData>,
+        }
+
+        // Transitory data is only present while the player is in game
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _TransitoryData {
+            data: Option<_Transitory>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Transitory {
+            #[serde(default)]
+            party_members: Vec<_PartyMember>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _PartyMember {
+            membership_id: String,
+            display_name: String,
+            emblem_hash: usize,
         }
 
         #[derive(Deserialize)]
@@ -122,6 +225,8 @@ impl<'de> Deserialize<'de> for ProfileCurrentActivities {
         }
 
         let profile = _Profile::deserialize(deserializer)?;
+        let transitory = profile.profile_transitory_data.and_then(|t| t.data);
+
         Ok(Self {
             privacy: profile.character_activities.privacy,
             activities: profile.character_activities.data.map(|d| {
@@ -137,6 +242,139 @@ impl<'de> Deserialize<'de> for ProfileCurrentActivities {
                     })
                     .collect()
             }),
+            online: transitory.is_some(),
+            fireteam: transitory
+                .map(|t| {
+                    t.party_members
+                        .into_iter()
+                        .map(|m| FireteamMember {
+                            membership_id: m.membership_id,
+                            display_name: m.display_name,
+                            emblem_hash: m.emblem_hash,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct ProfileCharacterMilestones {
+    pub privacy: usize,
+    pub milestones: Option<HashMap<String, Vec<CharacterMilestone>>>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CharacterMilestone {
+    pub milestone_hash: usize,
+    pub activity_hashes: Vec<usize>,
+    pub completed: bool,
+}
+
+impl<'de> Deserialize<'de> for ProfileCharacterMilestones {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Profile {
+            character_progressions: _CharacterProgressions,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _CharacterProgressions {
+            data: Option<HashMap<String, _Progression>>,
+            privacy: usize,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Progression {
+            milestones: HashMap<String, _Milestone>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Milestone {
+            milestone_hash: usize,
+            #[serde(default)]
+            activities: Vec<_MilestoneActivity>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _MilestoneActivity {
+            activity_hash: usize,
+            #[serde(default)]
+            challenges: Vec<_Challenge>,
+            #[serde(default)]
+            phases: Vec<_Phase>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Challenge {
+            objective: _Objective,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Objective {
+            complete: bool,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Phase {
+            complete: bool,
+        }
+
+        // A milestone only counts as completed once every challenge and
+        // encounter phase of every one of its activities has been completed
+        fn is_completed(activities: &[_MilestoneActivity]) -> bool {
+            let steps: Vec<bool> = activities
+                .iter()
+                .flat_map(|a| {
+                    a.challenges
+                        .iter()
+                        .map(|c| c.objective.complete)
+                        .chain(a.phases.iter().map(|p| p.complete))
+                })
+                .collect();
+
+            !steps.is_empty() && steps.into_iter().all(|c| c)
+        }
+
+        let profile = _Profile::deserialize(deserializer)?;
+        Ok(Self {
+            privacy: profile.character_progressions.privacy,
+            milestones: profile.character_progressions.data.map(|d| {
+                d.into_iter()
+                    .map(|(character_id, progression)| {
+                        (
+                            character_id,
+                            progression
+                                .milestones
+                                .into_values()
+                                .filter(|m| !m.activities.is_empty())
+                                .map(|m| CharacterMilestone {
+                                    milestone_hash: m.milestone_hash,
+                                    completed: is_completed(&m.activities),
+                                    activity_hashes: m
+                                        .activities
+                                        .iter()
+                                        .map(|a| a.activity_hash)
+                                        .collect(),
+                                })
+                                .collect(),
+                        )
+                    })
+                    .collect()
+            }),
         })
     }
 }
@@ -157,6 +395,7 @@ pub struct CompletedActivity {
     pub completed: bool,
     pub activity_duration: String,
     pub activity_duration_seconds: usize,
+    pub character: Option<CharacterInfo>,
 }
 
 impl PartialOrd for CompletedActivity {
@@ -228,6 +467,7 @@ impl<'de> Deserialize<'de> for CompletedActivity {
                 .display_value,
             activity_duration_seconds: activity.values.activity_duration_seconds.basic.value
                 as usize,
+            character: None,
         })
     }
 }
@@ -281,3 +521,87 @@ impl<'de> Deserialize<'de> for ActivityInfo {
         })
     }
 }
+
+// Fastest clear of every activity the character has completed
+#[derive(Debug, Clone)]
+pub struct AggregateActivityStats {
+    pub fastest_seconds: HashMap<usize, usize>,
+}
+
+impl<'de> Deserialize<'de> for AggregateActivityStats {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Stats {
+            #[serde(default)]
+            activities: Vec<_Activity>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Activity {
+            activity_hash: usize,
+            values: HashMap<String, _Value>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _Value {
+            basic: _BasicValue,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct _BasicValue {
+            value: f64,
+        }
+
+        let stats = _Stats::deserialize(deserializer)?;
+        Ok(Self {
+            fastest_seconds: stats
+                .activities
+                .into_iter()
+                .filter_map(|a| {
+                    let ms = a.values.get("fastestCompletionMsForActivity")?.basic.value;
+
+                    // Activities that were never completed report 0
+                    (ms > 0.0).then(|| (a.activity_hash, (ms / 1000.0) as usize))
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn aggregate_stats_skip_uncompleted_activities() {
+        let stats: AggregateActivityStats = serde_json::from_value(json!({
+            "activities": [
+                {
+                    "activityHash": 1,
+                    "values": {
+                        "fastestCompletionMsForActivity": { "basic": { "value": 754321.0 } },
+                        "activityCompletions": { "basic": { "value": 3.0 } }
+                    }
+                },
+                {
+                    "activityHash": 2,
+                    "values": {
+                        "fastestCompletionMsForActivity": { "basic": { "value": 0.0 } }
+                    }
+                }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(stats.fastest_seconds, HashMap::from([(1, 754)]));
+    }
+}
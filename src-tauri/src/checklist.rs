@@ -0,0 +1,144 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{
+    api::{
+        requests::BungieResponseError,
+        responses::{ActivityInfo, CharacterInfo},
+        Api, ApiError, Source,
+    },
+    config::profiles::Profile,
+    consts::WEEKLY_ACTIVITY_MODES,
+    pollers::playerdata::{fetch_history_since, weekly_reset},
+};
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WeeklyChecklist {
+    weekly_reset: DateTime<Utc>,
+    characters: Vec<CharacterChecklist>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct CharacterChecklist {
+    character_id: String,
+    character: Option<CharacterInfo>,
+    entries: Vec<ChecklistEntry>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ChecklistEntry {
+    activity_hash: usize,
+    activity_info: ActivityInfo,
+    cleared: bool,
+    milestone_completed: Option<bool>,
+}
+
+impl CharacterChecklist {
+    fn entry(&mut self, activity_hash: usize, activity_info: ActivityInfo) -> &mut ChecklistEntry {
+        let index = match self
+            .entries
+            .iter()
+            .position(|e| e.activity_hash == activity_hash)
+        {
+            Some(i) => i,
+            None => {
+                self.entries.push(ChecklistEntry {
+                    activity_hash,
+                    activity_info,
+                    cleared: false,
+                    milestone_completed: None,
+                });
+
+                self.entries.len() - 1
+            }
+        };
+
+        &mut self.entries[index]
+    }
+}
+
+pub async fn get_weekly_checklist(api: &Api, profile: &Profile) -> Result<WeeklyChecklist> {
+    let cutoff = weekly_reset()?;
+
+    let profile_info = api.profile_info_source.lock().await.get(profile).await?;
+
+    let milestones = match Api::get_profile_milestones(profile).await?.milestones {
+        Some(m) => m,
+        None => return Err(anyhow!("Profile is private")),
+    };
+
+    let character_infos = api
+        .character_info_source
+        .lock()
+        .await
+        .get(profile)
+        .await?
+        .characters
+        .unwrap_or_default();
+
+    let mut characters = Vec::new();
+
+    for character_id in profile_info.character_ids.iter() {
+        let mut checklist = CharacterChecklist {
+            character_id: character_id.clone(),
+            character: character_infos.get(character_id).cloned(),
+            entries: Vec::new(),
+        };
+
+        let history =
+            fetch_history_since(profile, character_id, cutoff, WEEKLY_ACTIVITY_MODES).await?;
+
+        for activity in history.into_iter().filter(|a| a.completed) {
+            let activity_info = api
+                .activity_info_source
+                .lock()
+                .await
+                .get(&activity.activity_hash)
+                .await?;
+
+            checklist
+                .entry(activity.activity_hash, activity_info)
+                .cleared = true;
+        }
+
+        // Milestones track weekly reward progress, which a clear in history
+        // doesn't always line up with (e.g. a run started from a checkpoint)
+        for milestone in milestones.get(character_id).into_iter().flatten() {
+            for activity_hash in milestone.activity_hashes.iter() {
+                let activity_info = match api
+                    .activity_info_source
+                    .lock()
+                    .await
+                    .get(activity_hash)
+                    .await
+                {
+                    Ok(a) => a,
+                    Err(ApiError::ResponseError(BungieResponseError::ResponseMissing)) => continue,
+                    Err(e) => return Err(e.into()),
+                };
+
+                if !activity_info
+                    .activity_modes
+                    .iter()
+                    .any(|m| WEEKLY_ACTIVITY_MODES.contains(m))
+                {
+                    continue;
+                }
+
+                let entry = checklist.entry(*activity_hash, activity_info);
+                entry.milestone_completed = Some(milestone.completed);
+            }
+        }
+
+        characters.push(checklist);
+    }
+
+    Ok(WeeklyChecklist {
+        weekly_reset: cutoff,
+        characters,
+    })
+}
@@ -0,0 +1,288 @@
+use std::{
+    fs::{read_to_string, write},
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use clap::{Parser, Subcommand};
+#[cfg(windows)]
+use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+use crate::{
+    api::{ProfileInfoSource, Source},
+    config::{bundle::ImportMode, profiles::Profile, ConfigManager},
+    consts::{APP_NAME, TRACKED_ACTIVITY_MODES},
+    daemon,
+    instance::{send_command, AppWindow, InstanceCommand, InstanceStatus, NotRunning},
+    paths::data_dir,
+    pollers::playerdata::fetch_history_since,
+};
+
+#[derive(Parser)]
+#[command(name = APP_NAME, version, about = "A Destiny 2 timer and tracker")]
+pub struct Cli {
+    #[arg(
+        long,
+        global = true,
+        value_name = "NAME#1234",
+        help = "Switch to a saved profile, by nickname, Bungie name or account id"
+    )]
+    pub profile: Option<String>,
+
+    #[arg(long, value_enum, value_name = "WINDOW", help = "Open a window")]
+    pub open: Option<AppWindow>,
+
+    #[arg(long, help = "Start without the overlay, until it's toggled back on")]
+    pub no_overlay: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["open", "no_overlay"],
+        help = "Track without any windows, tray or overlay, the same as the daemon subcommand"
+    )]
+    pub headless: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        help = "Keep config and other data in this directory"
+    )]
+    pub data_dir: Option<PathBuf>,
+
+    #[arg(long, global = true, help = "Keep data next to the executable")]
+    pub portable: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Show what the running instance is doing")]
+    Status,
+
+    #[command(about = "Write recent activity history as JSON")]
+    ExportHistory {
+        #[arg(
+            long,
+            short,
+            value_name = "FILE",
+            help = "Write to a file instead of stdout"
+        )]
+        output: Option<PathBuf>,
+
+        #[arg(
+            long,
+            default_value_t = 7,
+            value_parser = clap::value_parser!(i64).range(1..=3650),
+            help = "How many days of history to include"
+        )]
+        days: i64,
+    },
+
+    #[command(about = "Export preferences and saved profiles to a file")]
+    Export { file: PathBuf },
+
+    #[command(about = "Import preferences and saved profiles from a file")]
+    Import {
+        file: PathBuf,
+
+        #[arg(long, help = "Replace saved profiles instead of adding to them")]
+        replace_profiles: bool,
+    },
+
+    #[command(about = "Send a JSON command to the running instance")]
+    Send { command: String },
+
+    #[command(
+        about = "Track the selected profile without any windows, logging JSON lines to stdout"
+    )]
+    Daemon,
+}
+
+impl Cli {
+    // What a launch asks of an instance that's already running
+    pub fn launch_commands(&self) -> Vec<InstanceCommand> {
+        let mut commands = Vec::new();
+
+        if let Some(profile) = &self.profile {
+            commands.push(InstanceCommand::SwitchProfile {
+                profile: profile.clone(),
+            });
+        }
+
+        if let Some(window) = self.open {
+            commands.push(InstanceCommand::OpenWindow { window });
+        }
+
+        if commands.is_empty() {
+            commands.push(InstanceCommand::Activate);
+        }
+
+        commands
+    }
+
+    pub async fn send_launch_commands(&self) -> Result<()> {
+        // There's no command that only hides the overlay for the session
+        if self.no_overlay {
+            attach_console();
+            eprintln!(
+                "{APP_NAME} is already running, so --no-overlay was ignored. \
+                 Send {{\"command\":\"toggleOverlay\"}} to turn the overlay off"
+            );
+        }
+
+        for command in self.launch_commands() {
+            send_command(&command).await?;
+        }
+
+        Ok(())
+    }
+}
+
+// Subcommands run to completion without starting the app
+pub async fn run(command: Command, profile: Option<String>) -> Result<()> {
+    attach_console();
+
+    match command {
+        Command::Status => print_status().await,
+        Command::Daemon => daemon::run(profile).await,
+        Command::ExportHistory { output, days } => export_history(profile, output, days).await,
+        Command::Export { file } => {
+            let config = ConfigManager::load_read_only()?;
+            Ok(write(file, config.export_bundle()?)?)
+        }
+        Command::Import {
+            file,
+            replace_profiles,
+        } => {
+            let mode = if replace_profiles {
+                ImportMode::Replace
+            } else {
+                ImportMode::Merge
+            };
+
+            // A running instance picks up the new files by itself
+            let contents = read_to_string(file)?;
+            ConfigManager::load()?.import_bundle(&contents, mode)
+        }
+        Command::Send { command } => {
+            if let Some(data) = send_command(&serde_json::from_str(&command)?).await? {
+                println!("{}", serde_json::to_string_pretty(&data)?);
+            }
+
+            Ok(())
+        }
+    }
+}
+
+async fn print_status() -> Result<()> {
+    let status = match send_command(&InstanceCommand::Status).await {
+        Ok(Some(data)) => serde_json::from_value::<InstanceStatus>(data)?,
+        // Versions before status existed only acknowledge commands
+        Ok(None) => {
+            println!("{APP_NAME} is running");
+            return Ok(());
+        }
+        Err(e) if e.is::<NotRunning>() => {
+            let config = ConfigManager::load_read_only()?;
+
+            println!("{APP_NAME} isn't running");
+            println!("Data directory: {}", data_dir()?.display());
+            println!(
+                "Profile: {}",
+                selected_label(&config).unwrap_or("none".into())
+            );
+
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+
+    println!("{APP_NAME} v{} is running", status.version);
+    println!("Data directory: {}", status.data_dir);
+    println!("Profile: {}", status.profile.unwrap_or("none".into()));
+    println!(
+        "Overlay: {}",
+        if status.overlay_enabled {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+    println!("State: {}", status.state.unwrap_or("unknown".into()));
+
+    Ok(())
+}
+
+async fn export_history(query: Option<String>, output: Option<PathBuf>, days: i64) -> Result<()> {
+    let profile = {
+        let config = ConfigManager::load_read_only()?;
+        find_profile(&config, query.as_deref())?
+    };
+
+    let profile_info = ProfileInfoSource::get_value(profile.clone()).await?;
+    let cutoff = Utc::now() - chrono::Duration::days(days);
+
+    let mut history = Vec::new();
+
+    for character_id in profile_info.character_ids.iter() {
+        history.append(
+            &mut fetch_history_since(&profile, character_id, cutoff, TRACKED_ACTIVITY_MODES)
+                .await?,
+        );
+    }
+
+    history.sort();
+    history.reverse();
+
+    let json = serde_json::to_string_pretty(&history)?;
+
+    match output {
+        Some(path) => write(path, json)?,
+        None => println!("{json}"),
+    }
+
+    Ok(())
+}
+
+// The saved profile matching the query, or the selected one without a query
+pub fn find_profile(config: &ConfigManager, query: Option<&str>) -> Result<Profile> {
+    let profiles = config.get_profiles();
+
+    match query {
+        Some(q) => profiles
+            .find(q)
+            .map(|p| p.profile.clone())
+            .ok_or(anyhow!("No saved profile matches \"{q}\"")),
+        None => profiles
+            .selected_profile
+            .clone()
+            .ok_or(anyhow!("No profile set")),
+    }
+}
+
+pub fn selected_label(config: &ConfigManager) -> Option<String> {
+    let profiles = config.get_profiles();
+    let selected = profiles.selected_profile.as_ref()?;
+
+    profiles
+        .saved_profiles
+        .iter()
+        .find(|p| &p.profile == selected)
+        .map(|p| p.label())
+}
+
+// Release builds don't get a console of their own, so output only shows up
+// once attached to the one the command was run from
+#[cfg(windows)]
+fn attach_console() {
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
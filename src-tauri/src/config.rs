@@ -1,33 +1,134 @@
 use std::{
-    fs::{create_dir_all, read_to_string},
-    io::ErrorKind,
-    path::PathBuf,
+    fs::{copy, create_dir_all, read_to_string, rename, File},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
 };
 
-use anyhow::{anyhow, Result};
-use directories::BaseDirs;
+use anyhow::{anyhow, bail, Result};
 use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
 
-use self::{preferences::Preferences, profiles::Profiles};
-use crate::consts::APP_NAME;
+use self::{
+    bundle::{Bundle, ImportMode},
+    preferences::Preferences,
+    profiles::Profiles,
+};
+use crate::paths::data_dir;
 
+pub mod bundle;
+pub mod overrides;
 pub mod preferences;
 pub mod profiles;
+pub mod watcher;
 
 pub struct ConfigManager {
     preferences: Preferences,
     profiles: Profiles,
+    // Global preferences with the selected profile's overrides applied
+    effective_preferences: Preferences,
+    // Files written by a newer version are never overwritten
+    preferences_read_only: bool,
+    profiles_read_only: bool,
+    recovery_notices: Vec<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfigKind {
+    Preferences,
+    Profiles,
+}
+
+impl ConfigKind {
+    fn from_path(path: &Path) -> Option<Self> {
+        let filename = path.file_name()?;
+
+        if filename == Preferences::get_filename() {
+            Some(Self::Preferences)
+        } else if filename == Profiles::get_filename() {
+            Some(Self::Profiles)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Recovery {
+    Backup,
+    Defaults,
+}
+
+struct Loaded<T> {
+    config: T,
+    read_only: bool,
+    recovery: Option<Recovery>,
 }
 
 impl ConfigManager {
     pub fn load() -> Result<Self> {
+        let preferences = Preferences::load()?;
+        let profiles = Profiles::load()?;
+
+        let recovery_notices = [
+            (Preferences::get_filename(), preferences.recovery),
+            (Profiles::get_filename(), profiles.recovery),
+        ]
+        .into_iter()
+        .filter_map(|(filename, recovery)| match recovery? {
+            Recovery::Backup => Some(format!(
+                "{filename} was damaged and has been restored from its last backup."
+            )),
+            Recovery::Defaults => Some(format!(
+                "{filename} was damaged and has been reset to defaults."
+            )),
+        })
+        .collect();
+
+        let effective_preferences = overrides::resolve(
+            &preferences.config,
+            profiles.config.overrides_for_selected(),
+        );
+
         Ok(Self {
-            preferences: Preferences::load()?,
-            profiles: Profiles::load()?,
+            preferences: preferences.config,
+            profiles: profiles.config,
+            effective_preferences,
+            preferences_read_only: preferences.read_only,
+            profiles_read_only: profiles.read_only,
+            recovery_notices,
         })
     }
 
+    // For commands that only look at the config. Unlike load, nothing is
+    // written, migrated or recovered, and the config can't be changed
+    pub fn load_read_only() -> Result<Self> {
+        let preferences = Preferences::read()?;
+        let profiles = Profiles::read()?;
+
+        let effective_preferences =
+            overrides::resolve(&preferences, profiles.overrides_for_selected());
+
+        Ok(Self {
+            preferences,
+            profiles,
+            effective_preferences,
+            preferences_read_only: true,
+            profiles_read_only: true,
+            recovery_notices: Vec::new(),
+        })
+    }
+
+    // Messages for the user about config files that had to be recovered
+    pub fn take_recovery_notices(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.recovery_notices)
+    }
+
+    // The preferences in effect for the selected profile
     pub fn get_preferences(&self) -> &Preferences {
+        &self.effective_preferences
+    }
+
+    pub fn get_global_preferences(&self) -> &Preferences {
         &self.preferences
     }
 
@@ -35,56 +136,401 @@ impl ConfigManager {
         &self.profiles
     }
 
-    pub fn set_preferences(&mut self, preferences: Preferences) -> Result<()> {
+    pub fn set_preferences(&mut self, mut preferences: Preferences) -> Result<()> {
+        if self.preferences_read_only {
+            bail!(NEWER_VERSION_ERROR);
+        }
+
+        preferences.local_api.ensure_token();
+
         self.preferences = preferences;
+        self.resolve_preferences();
         self.preferences.write()
     }
 
-    pub fn set_profiles(&mut self, profiles: Profiles) -> Result<()> {
+    pub fn set_profiles(&mut self, mut profiles: Profiles) -> Result<()> {
+        if self.profiles_read_only {
+            bail!(NEWER_VERSION_ERROR);
+        }
+
+        if profiles.selected_profile != self.profiles.selected_profile {
+            profiles.mark_selected_used();
+        }
+
         self.profiles = profiles;
+        self.resolve_preferences();
         self.profiles.write()
     }
+
+    // Stores the differences from the global preferences as overrides for
+    // the selected profile
+    pub fn set_profile_preferences(&mut self, mut preferences: Preferences) -> Result<()> {
+        let profile = match &self.profiles.selected_profile {
+            Some(p) => p.clone(),
+            None => bail!("No profile set"),
+        };
+
+        preferences.local_api.ensure_token();
+
+        let mut profiles = self.profiles.clone();
+        profiles.set_overrides(&profile, overrides::diff(&self.preferences, &preferences)?);
+
+        self.set_profiles(profiles)
+    }
+
+    fn resolve_preferences(&mut self) {
+        self.effective_preferences =
+            overrides::resolve(&self.preferences, self.profiles.overrides_for_selected());
+    }
+
+    pub fn export_bundle(&self) -> Result<String> {
+        Bundle::new(&self.preferences, &self.profiles)?.to_string()
+    }
+
+    // Replaces the preferences with those in the bundle, and either merges or
+    // replaces saved profiles
+    pub fn import_bundle(&mut self, contents: &str, mode: ImportMode) -> Result<()> {
+        if self.preferences_read_only || self.profiles_read_only {
+            bail!(NEWER_VERSION_ERROR);
+        }
+
+        let (preferences, profiles) = Bundle::parse(contents)?;
+
+        let profiles = match mode {
+            ImportMode::Merge => self.profiles.merged(profiles)?,
+            ImportMode::Replace => profiles,
+        };
+
+        self.set_preferences(preferences)?;
+        self.set_profiles(profiles)
+    }
+
+    // Picks up edits made to the preferences file outside of the app.
+    // Returns whether they differ from the loaded preferences
+    pub fn reload_preferences(&mut self) -> Result<bool> {
+        match self.preferences.reload()? {
+            Some((preferences, read_only)) => {
+                self.preferences = preferences;
+                self.preferences_read_only = read_only;
+                self.resolve_preferences();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn reload_profiles(&mut self) -> Result<bool> {
+        match self.profiles.reload()? {
+            Some((profiles, read_only)) => {
+                self.profiles = profiles;
+                self.profiles_read_only = read_only;
+                self.resolve_preferences();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
 }
 
+const NEWER_VERSION_ERROR: &str =
+    "Config was written by a newer version of threepole, update to change it";
+
+// Upgrades a config object from the version at its index in
+// ConfigFile::migrations to the next one
+type Migration = fn(&mut Map<String, Value>);
+
 trait ConfigFile: Serialize + DeserializeOwned + Default {
-    fn load() -> Result<Self> {
-        match read_to_string(Self::get_path()?) {
-            Ok(s) => {
-                let def = serde_json::from_str::<Self>(&s)?;
-                def.write()?;
-                Ok(def)
-            }
+    fn load() -> Result<Loaded<Self>> {
+        Self::load_from(&Self::get_path()?)
+    }
+
+    fn load_from(path: &Path) -> Result<Loaded<Self>> {
+        let contents = match read_to_string(path) {
+            Ok(s) => s,
             Err(e) => match e.kind() {
                 ErrorKind::NotFound => {
                     let def = Self::default();
-                    def.write()?;
-                    Ok(def)
+                    def.write_to(path)?;
+
+                    return Ok(Loaded {
+                        config: def,
+                        read_only: false,
+                        recovery: None,
+                    });
                 }
-                _ => Err(e.into()),
+                _ => return Err(e.into()),
             },
+        };
+
+        if let Ok((def, read_only)) = Self::parse(&contents) {
+            if !read_only {
+                def.write_to(path)?;
+            }
+
+            return Ok(Loaded {
+                config: def,
+                read_only,
+                recovery: None,
+            });
+        }
+
+        // Keep the damaged file around rather than overwriting it
+        rename(path, with_suffix(path, "corrupt"))?;
+
+        let backup = read_to_string(with_suffix(path, "bak"))
+            .ok()
+            .and_then(|s| Self::parse(&s).ok());
+
+        let (def, recovery) = match backup {
+            Some((def, false)) => (def, Recovery::Backup),
+            _ => (Self::default(), Recovery::Defaults),
+        };
+
+        def.write_to(path)?;
+
+        Ok(Loaded {
+            config: def,
+            read_only: false,
+            recovery: Some(recovery),
+        })
+    }
+
+    fn read() -> Result<Self> {
+        Self::read_from(&Self::get_path()?)
+    }
+
+    // Damaged files are read from their backup, or else the defaults
+    fn read_from(path: &Path) -> Result<Self> {
+        let contents = match read_to_string(path) {
+            Ok(s) => s,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let parsed = Self::parse(&contents).ok().or_else(|| {
+            read_to_string(with_suffix(path, "bak"))
+                .ok()
+                .and_then(|s| Self::parse(&s).ok())
+        });
+
+        Ok(parsed.map(|(config, _)| config).unwrap_or_default())
+    }
+
+    // Unlike load, a file that fails to parse is left alone, as it may be
+    // halfway through being edited. Our own writes come back unchanged and
+    // are skipped
+    fn reload(&self) -> Result<Option<(Self, bool)>> {
+        let (config, read_only) = Self::parse(&read_to_string(Self::get_path()?)?)?;
+
+        if serde_json::to_value(&config)? == serde_json::to_value(self)? {
+            return Ok(None);
         }
+
+        Ok(Some((config, read_only)))
+    }
+
+    // Returns the config, and whether it was written by a newer version and
+    // so must be left untouched
+    fn parse(contents: &str) -> Result<(Self, bool)> {
+        Self::from_object(serde_json::from_str(contents)?)
     }
 
+    fn from_object(mut value: Map<String, Value>) -> Result<(Self, bool)> {
+        let version = match value.get("version") {
+            Some(v) => v.as_u64().ok_or(anyhow!("Invalid config version"))? as usize,
+            None => 0,
+        };
+
+        if version > Self::version() {
+            // Best effort, as fields may have changed in ways this version
+            // doesn't understand
+            let def = serde_json::from_value(Value::Object(value)).unwrap_or_default();
+            return Ok((def, true));
+        }
+
+        for migration in &Self::migrations()[version..] {
+            migration(&mut value);
+        }
+
+        Ok((serde_json::from_value(Value::Object(value))?, false))
+    }
+
+    // Writes to a temporary file first and renames it over the old one, so a
+    // crash mid-write can't leave a truncated file behind. The old file is
+    // kept as a backup as long as it's still readable
     fn write(&self) -> Result<()> {
-        let path = Self::get_path()?;
-        let mut dir = path.clone();
-        dir.pop();
+        self.write_to(&Self::get_path()?)
+    }
 
-        create_dir_all(dir)?;
+    fn write_to(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
+        }
 
-        Ok(std::fs::write(path, serde_json::to_string(&self)?)?)
+        let value = self.to_object()?;
+
+        let temp_path = with_suffix(path, "tmp");
+
+        let mut file = File::create(&temp_path)?;
+        file.write_all(serde_json::to_string(&value)?.as_bytes())?;
+        file.sync_all()?;
+
+        let current_valid = read_to_string(path)
+            .map(|s| serde_json::from_str::<Value>(&s).is_ok())
+            .unwrap_or(false);
+
+        if current_valid {
+            copy(path, with_suffix(path, "bak"))?;
+        }
+
+        Ok(rename(temp_path, path)?)
+    }
+
+    // The config as it's stored, tagged with its version
+    fn to_object(&self) -> Result<Map<String, Value>> {
+        let mut value = match serde_json::to_value(self)? {
+            Value::Object(v) => v,
+            _ => bail!("Config must serialize to an object"),
+        };
+
+        value.insert("version".to_string(), Self::version().into());
+
+        Ok(value)
+    }
+
+    fn version() -> usize {
+        Self::migrations().len()
     }
 
     fn get_path() -> Result<PathBuf> {
-        BaseDirs::new()
-            .map(|d| {
-                let mut path = d.data_dir().to_owned();
-                path.push(APP_NAME);
-                path.push(Self::get_filename());
-                path
-            })
-            .ok_or(anyhow!("No data_dir available"))
+        Ok(data_dir()?.join(Self::get_filename()))
     }
 
     fn get_filename() -> &'static str;
+
+    fn migrations() -> &'static [Migration];
+}
+
+// e.g. preferences.json -> preferences.json.bak
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(suffix);
+    path.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn preferences_path(dir: &TempDir) -> PathBuf {
+        dir.path().join(Preferences::get_filename())
+    }
+
+    fn read_object(path: &Path) -> Value {
+        serde_json::from_str(&read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn older_files_are_migrated_and_rewritten() {
+        let dir = TempDir::new().unwrap();
+        let path = preferences_path(&dir);
+
+        std::fs::write(&path, json!({ "displayDailyClears": false }).to_string()).unwrap();
+
+        let loaded = Preferences::load_from(&path).unwrap();
+
+        assert!(!loaded.read_only);
+        assert!(!loaded.config.widgets.daily_clears.enabled);
+
+        let written = read_object(&path);
+
+        assert_eq!(written["version"], json!(Preferences::version()));
+        assert!(written.get("displayDailyClears").is_none());
+    }
+
+    #[test]
+    fn newer_files_are_read_only() {
+        let dir = TempDir::new().unwrap();
+        let path = preferences_path(&dir);
+
+        let contents = json!({ "version": Preferences::version() + 1, "enableOverlay": false });
+        std::fs::write(&path, contents.to_string()).unwrap();
+
+        let loaded = Preferences::load_from(&path).unwrap();
+
+        assert!(loaded.read_only);
+        assert!(!loaded.config.enable_overlay);
+        assert_eq!(read_object(&path), contents);
+    }
+
+    #[test]
+    fn damaged_files_are_restored_from_backup() {
+        let dir = TempDir::new().unwrap();
+        let path = preferences_path(&dir);
+
+        let backup = Preferences {
+            enable_overlay: true,
+            ..Default::default()
+        };
+        backup.write_to(&path).unwrap();
+
+        // The second write keeps the first as the backup
+        Preferences::default().write_to(&path).unwrap();
+        std::fs::write(&path, "{ \"enableOverlay\": ").unwrap();
+
+        let loaded = Preferences::load_from(&path).unwrap();
+
+        assert!(matches!(loaded.recovery, Some(Recovery::Backup)));
+        assert!(loaded.config.enable_overlay);
+        assert_eq!(
+            read_to_string(with_suffix(&path, "corrupt")).unwrap(),
+            "{ \"enableOverlay\": "
+        );
+        assert_eq!(read_object(&path)["enableOverlay"], json!(true));
+    }
+
+    #[test]
+    fn damaged_files_without_backup_are_reset() {
+        let dir = TempDir::new().unwrap();
+        let path = preferences_path(&dir);
+
+        std::fs::write(&path, json!({ "displayMilliseconds": 1 }).to_string()).unwrap();
+
+        let loaded = Preferences::load_from(&path).unwrap();
+
+        assert!(matches!(loaded.recovery, Some(Recovery::Defaults)));
+        assert!(loaded.config.display_milliseconds);
+        assert!(with_suffix(&path, "corrupt").exists());
+    }
+
+    #[test]
+    fn reading_leaves_files_alone() {
+        let dir = TempDir::new().unwrap();
+        let path = preferences_path(&dir);
+
+        assert!(Preferences::read_from(&path).is_ok());
+        assert!(!path.exists());
+
+        let old = json!({ "displayDailyClears": false }).to_string();
+        std::fs::write(&path, &old).unwrap();
+
+        let preferences = Preferences::read_from(&path).unwrap();
+
+        assert!(!preferences.widgets.daily_clears.enabled);
+        assert_eq!(read_to_string(&path).unwrap(), old);
+
+        std::fs::write(&path, "{").unwrap();
+
+        assert!(Preferences::read_from(&path).is_ok());
+        assert!(!with_suffix(&path, "corrupt").exists());
+    }
+
+    #[test]
+    fn invalid_versions_fail_to_parse() {
+        assert!(Preferences::parse(&json!({ "version": "1" }).to_string()).is_err());
+    }
 }
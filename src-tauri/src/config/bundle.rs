@@ -0,0 +1,59 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::{preferences::Preferences, profiles::Profiles, ConfigFile};
+use crate::consts::BUNDLE_VERSION;
+
+// Preferences and profiles in a single file, for moving a setup between
+// machines. Each config keeps its own version so older exports go through
+// the same migrations as config files
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bundle {
+    bundle_version: usize,
+    preferences: Map<String, Value>,
+    profiles: Map<String, Value>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportMode {
+    // Adds the bundle's saved profiles to the current ones
+    Merge,
+    Replace,
+}
+
+impl Bundle {
+    pub fn new(preferences: &Preferences, profiles: &Profiles) -> Result<Self> {
+        Ok(Self {
+            bundle_version: BUNDLE_VERSION,
+            preferences: preferences.to_object()?,
+            profiles: profiles.to_object()?,
+        })
+    }
+
+    pub fn parse(contents: &str) -> Result<(Preferences, Profiles)> {
+        let bundle: Bundle = serde_json::from_str(contents)?;
+
+        if bundle.bundle_version > BUNDLE_VERSION {
+            bail!(NEWER_BUNDLE_ERROR);
+        }
+
+        let (preferences, preferences_newer) = Preferences::from_object(bundle.preferences)?;
+        let (profiles, profiles_newer) = Profiles::from_object(bundle.profiles)?;
+
+        if preferences_newer || profiles_newer {
+            bail!(NEWER_BUNDLE_ERROR);
+        }
+
+        Ok((preferences, profiles))
+    }
+
+    pub fn to_string(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+const NEWER_BUNDLE_ERROR: &str =
+    "Settings were exported by a newer version of threepole, update to import them";
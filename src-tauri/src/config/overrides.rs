@@ -0,0 +1,153 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::{preferences::Preferences, profiles::Profile};
+
+// Preferences that differ from the global ones while a profile is selected.
+// Only the changed fields are stored, so later changes to the rest of the
+// global preferences still apply to the profile
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PreferenceOverride {
+    pub profile: Profile,
+    pub preferences: Map<String, Value>,
+}
+
+// Overrides that no longer fit the preferences, e.g. after a field changed
+// type, are ignored rather than breaking the profile
+pub fn resolve(global: &Preferences, overrides: Option<&Map<String, Value>>) -> Preferences {
+    let overrides = match overrides {
+        Some(o) => o,
+        None => return global.clone(),
+    };
+
+    let resolved = serde_json::to_value(global).and_then(|mut value| {
+        merge(&mut value, overrides);
+        serde_json::from_value(value)
+    });
+
+    resolved.unwrap_or_else(|_| global.clone())
+}
+
+// The fields of preferences that differ from the global ones
+pub fn diff(global: &Preferences, preferences: &Preferences) -> Result<Map<String, Value>> {
+    match diff_value(
+        &serde_json::to_value(global)?,
+        &serde_json::to_value(preferences)?,
+    ) {
+        Some(Value::Object(o)) => Ok(o),
+        None => Ok(Map::new()),
+        Some(_) => bail!("Preferences must serialize to an object"),
+    }
+}
+
+fn merge(value: &mut Value, overrides: &Map<String, Value>) {
+    let object = match value {
+        Value::Object(o) => o,
+        _ => return,
+    };
+
+    for (key, override_value) in overrides {
+        match (object.get_mut(key), override_value) {
+            (Some(v @ Value::Object(_)), Value::Object(o)) => merge(v, o),
+            _ => {
+                object.insert(key.clone(), override_value.clone());
+            }
+        }
+    }
+}
+
+fn diff_value(base: &Value, value: &Value) -> Option<Value> {
+    match (base, value) {
+        (Value::Object(base), Value::Object(value)) => {
+            let changed: Map<String, Value> = value
+                .iter()
+                .filter_map(|(key, v)| {
+                    let changed = match base.get(key) {
+                        Some(b) => diff_value(b, v)?,
+                        None => v.clone(),
+                    };
+
+                    Some((key.clone(), changed))
+                })
+                .collect();
+
+            if changed.is_empty() {
+                None
+            } else {
+                Some(Value::Object(changed))
+            }
+        }
+        _ if base == value => None,
+        _ => Some(value.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn object(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn diff_only_keeps_changed_fields() {
+        let global = Preferences::default();
+
+        let mut preferences = global.clone();
+        preferences.enable_overlay = !global.enable_overlay;
+        preferences.widgets.timer.scale = 2.0;
+
+        assert_eq!(
+            Value::Object(diff(&global, &preferences).unwrap()),
+            json!({
+                "enableOverlay": preferences.enable_overlay,
+                "widgets": { "timer": { "scale": 2.0 } },
+            })
+        );
+        assert!(diff(&global, &global).unwrap().is_empty());
+    }
+
+    #[test]
+    fn resolve_merges_nested_overrides() {
+        let global = Preferences::default();
+
+        let resolved = resolve(
+            &global,
+            Some(&object(json!({ "widgets": { "timer": { "scale": 2.0 } } }))),
+        );
+
+        assert_eq!(resolved.widgets.timer.scale, 2.0);
+        assert!(resolved.widgets.timer.enabled == global.widgets.timer.enabled);
+        assert!(resolved.widgets.fireteam == global.widgets.fireteam);
+    }
+
+    #[test]
+    fn resolve_ignores_overrides_that_no_longer_fit() {
+        let global = Preferences::default();
+
+        let resolved = resolve(&global, Some(&object(json!({ "enableOverlay": "yes" }))));
+
+        assert_eq!(resolved.enable_overlay, global.enable_overlay);
+    }
+
+    #[test]
+    fn diff_and_resolve_round_trip() {
+        let global = Preferences::default();
+
+        let mut preferences = global.clone();
+        preferences.display_milliseconds = !global.display_milliseconds;
+        preferences.visibility.hide_after_inactive_secs = 300;
+
+        let resolved = resolve(&global, Some(&diff(&global, &preferences).unwrap()));
+
+        assert_eq!(
+            serde_json::to_value(resolved).unwrap(),
+            serde_json::to_value(preferences).unwrap()
+        );
+    }
+}
@@ -1,24 +1,269 @@
-use serde::{Deserialize, Serialize};
+use glob::{MatchOptions, Pattern};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
 
-use super::ConfigFile;
+use super::{ConfigFile, Migration};
+use crate::consts::LOCAL_API_TOKEN_LEN;
 
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 #[serde(default)]
 pub struct Preferences {
     pub enable_overlay: bool,
-    pub display_daily_clears: bool,
-    pub display_clear_notifications: bool,
     pub display_milliseconds: bool,
+    // Clears per milestone notification, 0 to turn them off
+    pub daily_clear_milestone: usize,
+    pub polling: PollingPreferences,
+    pub game_targets: GameTargets,
+    pub widgets: WidgetsPreferences,
+    pub visibility: VisibilityPreferences,
+    pub local_api: LocalApiPreferences,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct PollingPreferences {
+    pub active_interval_secs: u64,
+    pub idle_interval_secs: u64,
+    pub offline_interval_secs: u64,
+    pub post_activity_interval_secs: u64,
+    pub idle_after_secs: u64,
+    pub post_activity_window_secs: u64,
+    pub history_poll_ratio: usize,
+    pub suspend_when_game_closed: bool,
 }
 
 impl Default for Preferences {
     fn default() -> Self {
         Self {
             enable_overlay: false,
-            display_daily_clears: true,
-            display_clear_notifications: true,
             display_milliseconds: true,
+            daily_clear_milestone: 5,
+            polling: PollingPreferences::default(),
+            game_targets: GameTargets::default(),
+            widgets: WidgetsPreferences::default(),
+            visibility: VisibilityPreferences::default(),
+            local_api: LocalApiPreferences::default(),
+        }
+    }
+}
+
+// Serves player data to stream overlays and other tools on this machine
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct LocalApiPreferences {
+    pub enabled: bool,
+    pub port: u16,
+    // Required as a bearer token or ?token= query parameter
+    pub token: String,
+    // Web page origins allowed to open the WebSocket, besides browser
+    // sources loaded from local files
+    pub allowed_origins: Vec<String>,
+}
+
+impl Default for LocalApiPreferences {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 7263,
+            token: String::new(),
+            allowed_origins: Vec::new(),
+        }
+    }
+}
+
+impl LocalApiPreferences {
+    // The API is never served without a token, so one is picked when it's
+    // enabled without
+    pub fn ensure_token(&mut self) {
+        if self.enabled && self.token.is_empty() {
+            self.token = thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(LOCAL_API_TOKEN_LEN)
+                .map(char::from)
+                .collect();
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct VisibilityPreferences {
+    pub only_in_tracked_activities: bool,
+    pub hide_in_orbit: bool,
+    // 0 disables hiding on inactivity
+    pub hide_after_inactive_secs: u64,
+    pub show_after_clear: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct WidgetsPreferences {
+    pub timer: WidgetPreferences,
+    pub daily_clears: WidgetPreferences,
+    pub fireteam: WidgetPreferences,
+    pub notifications: WidgetPreferences,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct WidgetPreferences {
+    pub enabled: bool,
+    pub anchor: OverlayAnchor,
+    pub offset_x: OverlayOffset,
+    pub offset_y: OverlayOffset,
+    pub scale: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum OverlayAnchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    AboveMinimap,
+    // Offsets are measured from the top left of the game window, without the
+    // preset's margins
+    Custom,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlayOffset {
+    pub value: f64,
+    pub unit: OffsetUnit,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum OffsetUnit {
+    Px,
+    Percent,
+}
+
+impl Default for VisibilityPreferences {
+    fn default() -> Self {
+        Self {
+            only_in_tracked_activities: false,
+            hide_in_orbit: false,
+            hide_after_inactive_secs: 0,
+            show_after_clear: true,
+        }
+    }
+}
+
+impl Default for OffsetUnit {
+    fn default() -> Self {
+        Self::Px
+    }
+}
+
+impl Default for WidgetsPreferences {
+    fn default() -> Self {
+        Self {
+            timer: WidgetPreferences::default(),
+            daily_clears: WidgetPreferences {
+                offset_y: OverlayOffset {
+                    value: 40.0,
+                    unit: OffsetUnit::Px,
+                },
+                ..Default::default()
+            },
+            fireteam: WidgetPreferences {
+                enabled: false,
+                anchor: OverlayAnchor::TopRight,
+                ..Default::default()
+            },
+            notifications: WidgetPreferences {
+                anchor: OverlayAnchor::BottomRight,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl Default for WidgetPreferences {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            anchor: OverlayAnchor::AboveMinimap,
+            offset_x: OverlayOffset::default(),
+            offset_y: OverlayOffset::default(),
+            scale: 1.0,
+        }
+    }
+}
+
+// Executable names or window classes identifying the game, matched
+// case-insensitively as glob patterns
+#[derive(Clone)]
+pub struct GameTargets(Vec<Pattern>);
+
+impl GameTargets {
+    pub fn matches(&self, name: &str) -> bool {
+        let options = MatchOptions {
+            case_sensitive: false,
+            ..Default::default()
+        };
+
+        self.0.iter().any(|p| p.matches_with(name, options))
+    }
+}
+
+impl Default for GameTargets {
+    fn default() -> Self {
+        Self(vec![Pattern::new("destiny2.exe").unwrap()])
+    }
+}
+
+impl Serialize for GameTargets {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.0.iter().map(|p| p.as_str()))
+    }
+}
+
+impl<'de> Deserialize<'de> for GameTargets {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let targets = Vec::<String>::deserialize(deserializer)?;
+
+        // Anything that isn't a valid glob is matched literally
+        Ok(Self(
+            targets
+                .iter()
+                .map(|t| t.trim())
+                .filter(|t| !t.is_empty())
+                .map(|t| {
+                    Pattern::new(t).unwrap_or_else(|_| Pattern::new(&Pattern::escape(t)).unwrap())
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl Default for PollingPreferences {
+    fn default() -> Self {
+        Self {
+            active_interval_secs: 2,
+            idle_interval_secs: 10,
+            offline_interval_secs: 30,
+            post_activity_interval_secs: 1,
+            idle_after_secs: 15 * 60,
+            post_activity_window_secs: 2 * 60,
+            history_poll_ratio: 5,
+            suspend_when_game_closed: false,
         }
     }
 }
@@ -27,4 +272,117 @@ impl ConfigFile for Preferences {
     fn get_filename() -> &'static str {
         "preferences.json"
     }
+
+    fn migrations() -> &'static [Migration] {
+        &[migrate_overlay_widgets]
+    }
+}
+
+// Overlay display toggles and placement became per-widget preferences
+fn migrate_overlay_widgets(preferences: &mut Map<String, Value>) {
+    let mut widgets = serde_json::to_value(WidgetsPreferences::default()).unwrap();
+
+    if let Some(Value::Object(overlay)) = preferences.remove("overlay") {
+        for (key, value) in overlay {
+            widgets["timer"][key] = value;
+        }
+    }
+
+    if let Some(v) = preferences.remove("displayDailyClears") {
+        widgets["dailyClears"]["enabled"] = v;
+    }
+
+    if let Some(v) = preferences.remove("displayClearNotifications") {
+        widgets["notifications"]["enabled"] = v;
+    }
+
+    preferences.insert("widgets".to_string(), widgets);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn targets(patterns: Value) -> GameTargets {
+        serde_json::from_value(patterns).unwrap()
+    }
+
+    #[test]
+    fn game_targets_match_case_insensitive_globs() {
+        let targets = targets(json!(["destiny2.exe", "Destiny*", " ", "steam_app_?"]));
+
+        assert!(targets.matches("Destiny2.EXE"));
+        assert!(targets.matches("destiny2-launcher"));
+        assert!(targets.matches("steam_app_1"));
+        assert!(!targets.matches("steam_app_12"));
+        assert!(!targets.matches(""));
+        assert_eq!(
+            serde_json::to_value(&targets)
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn invalid_game_targets_match_literally() {
+        let targets = targets(json!(["[destiny"]));
+
+        assert!(targets.matches("[Destiny"));
+        assert!(!targets.matches("destiny"));
+    }
+
+    #[test]
+    fn overlay_toggles_migrate_to_widgets() {
+        let (preferences, read_only) = Preferences::parse(
+            &json!({
+                "enableOverlay": true,
+                "displayDailyClears": false,
+                "displayClearNotifications": true,
+                "overlay": { "anchor": "topRight", "scale": 1.5 },
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let widgets = preferences.widgets;
+
+        assert!(!read_only);
+        assert!(!widgets.daily_clears.enabled);
+        assert!(widgets.notifications.enabled);
+        assert!(widgets.timer.anchor == OverlayAnchor::TopRight);
+        assert_eq!(widgets.timer.scale, 1.5);
+    }
+
+    #[test]
+    fn migrated_preferences_are_left_alone() {
+        let mut value = Value::Object(Preferences::default().to_object().unwrap());
+        value["widgets"]["dailyClears"]["enabled"] = json!(false);
+
+        let (preferences, _) = Preferences::parse(&value.to_string()).unwrap();
+
+        assert!(!preferences.widgets.daily_clears.enabled);
+        assert!(preferences.widgets.timer.enabled);
+    }
+
+    #[test]
+    fn enabling_the_local_api_generates_a_token() {
+        let mut local_api = LocalApiPreferences::default();
+
+        local_api.ensure_token();
+        assert!(local_api.token.is_empty());
+
+        local_api.enabled = true;
+        local_api.ensure_token();
+
+        let token = local_api.token.clone();
+        assert_eq!(token.len(), LOCAL_API_TOKEN_LEN);
+
+        local_api.ensure_token();
+        assert_eq!(local_api.token, token);
+    }
 }
@@ -1,14 +1,48 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Map, Value};
 
-use super::ConfigFile;
+use super::{overrides::PreferenceOverride, ConfigFile, Migration};
 
 #[derive(Serialize, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 #[serde(default)]
 pub struct Profiles {
-    pub saved_profiles: Vec<Profile>,
+    pub saved_profiles: Vec<SavedProfile>,
     pub selected_profile: Option<Profile>,
+    pub preference_overrides: Vec<PreferenceOverride>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedProfile {
+    #[serde(flatten)]
+    pub profile: Profile,
+    // Cached from the API so the profile list can be shown right away, and
+    // refreshed in the background
+    pub display_name: Option<String>,
+    pub display_tag: Option<usize>,
+    pub nickname: Option<String>,
+    pub last_used: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl SavedProfile {
+    // The nickname if there is one, otherwise the cached Bungie name
+    pub fn label(&self) -> String {
+        if let Some(nickname) = &self.nickname {
+            return nickname.clone();
+        }
+
+        match (&self.display_name, self.display_tag) {
+            (Some(name), Some(tag)) => format!("{name}#{tag:04}"),
+            (Some(name), None) => name.clone(),
+            _ => self.profile.account_id.clone(),
+        }
+    }
 }
 
 impl<'de> Deserialize<'de> for Profiles {
@@ -19,18 +53,131 @@ impl<'de> Deserialize<'de> for Profiles {
         #[derive(Deserialize)]
         #[serde(rename_all = "camelCase")]
         struct _Profiles {
-            saved_profiles: Vec<Profile>,
+            saved_profiles: Vec<SavedProfile>,
             selected_profile: Option<Profile>,
+            #[serde(default)]
+            preference_overrides: Vec<PreferenceOverride>,
         }
 
         let profiles = _Profiles::deserialize(deserializer)?;
         Ok(Self {
-            saved_profiles: profiles.saved_profiles.into_iter().unique().collect(),
+            saved_profiles: profiles
+                .saved_profiles
+                .into_iter()
+                .unique_by(|p| p.profile.clone())
+                .collect(),
             selected_profile: profiles.selected_profile,
+            preference_overrides: profiles
+                .preference_overrides
+                .into_iter()
+                .unique_by(|o| o.profile.clone())
+                .collect(),
         })
     }
 }
 
+impl Profiles {
+    // Looks up a saved profile by nickname, display name with or without its
+    // tag, or account id, ignoring case
+    pub fn find(&self, query: &str) -> Option<&SavedProfile> {
+        let query = query.to_lowercase();
+
+        self.saved_profiles.iter().find(|p| {
+            let mut names = vec![p.profile.account_id.clone()];
+
+            names.extend(p.nickname.clone());
+
+            if let Some(name) = &p.display_name {
+                names.push(name.clone());
+                names.extend(p.display_tag.map(|t| format!("{name}#{t:04}")));
+            }
+
+            names.iter().any(|n| n.to_lowercase() == query)
+        })
+    }
+
+    pub fn mark_selected_used(&mut self) {
+        let selected = match &self.selected_profile {
+            Some(p) => p,
+            None => return,
+        };
+
+        for saved in self.saved_profiles.iter_mut() {
+            if &saved.profile == selected {
+                saved.last_used = Some(Utc::now());
+            }
+        }
+    }
+
+    // Returns whether the cached name of the profile changed
+    pub fn set_display_name(&mut self, profile: &Profile, name: &str, tag: usize) -> bool {
+        let mut changed = false;
+
+        for saved in self.saved_profiles.iter_mut() {
+            if &saved.profile == profile
+                && (saved.display_name.as_deref() != Some(name) || saved.display_tag != Some(tag))
+            {
+                saved.display_name = Some(name.to_string());
+                saved.display_tag = Some(tag);
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    pub fn overrides_for(&self, profile: &Profile) -> Option<&Map<String, Value>> {
+        self.preference_overrides
+            .iter()
+            .find(|o| &o.profile == profile)
+            .map(|o| &o.preferences)
+    }
+
+    pub fn overrides_for_selected(&self) -> Option<&Map<String, Value>> {
+        self.overrides_for(self.selected_profile.as_ref()?)
+    }
+
+    // Empty overrides remove the profile's entry
+    pub fn set_overrides(&mut self, profile: &Profile, preferences: Map<String, Value>) {
+        self.preference_overrides.retain(|o| &o.profile != profile);
+
+        if !preferences.is_empty() {
+            self.preference_overrides.push(PreferenceOverride {
+                profile: profile.clone(),
+                preferences,
+            });
+        }
+    }
+
+    // Saved profiles and overrides of both, going through deserialization to
+    // drop duplicates. The current selection and overrides win over the
+    // other ones
+    pub fn merged(&self, other: Profiles) -> Result<Self> {
+        let saved_profiles: Vec<&SavedProfile> = self
+            .saved_profiles
+            .iter()
+            .chain(other.saved_profiles.iter())
+            .collect();
+
+        let selected_profile = self
+            .selected_profile
+            .as_ref()
+            .or(other.selected_profile.as_ref());
+
+        let preference_overrides: Vec<&PreferenceOverride> = self
+            .preference_overrides
+            .iter()
+            .chain(other.preference_overrides.iter())
+            .collect();
+
+        Ok(serde_json::from_value(json!({
+            "savedProfiles": saved_profiles,
+            "selectedProfile": selected_profile,
+            "preferenceOverrides": preference_overrides,
+        }))?)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct Profile {
@@ -42,4 +189,106 @@ impl ConfigFile for Profiles {
     fn get_filename() -> &'static str {
         "profiles.json"
     }
+
+    fn migrations() -> &'static [Migration] {
+        &[]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(account_id: &str) -> Profile {
+        Profile {
+            account_platform: 3,
+            account_id: account_id.to_string(),
+        }
+    }
+
+    fn saved(account_id: &str, nickname: Option<&str>) -> SavedProfile {
+        SavedProfile {
+            profile: profile(account_id),
+            display_name: None,
+            display_tag: None,
+            nickname: nickname.map(|n| n.to_string()),
+            last_used: None,
+            pinned: false,
+        }
+    }
+
+    fn overrides(account_id: &str, enable_overlay: bool) -> PreferenceOverride {
+        PreferenceOverride {
+            profile: profile(account_id),
+            preferences: json!({ "enableOverlay": enable_overlay })
+                .as_object()
+                .unwrap()
+                .clone(),
+        }
+    }
+
+    #[test]
+    fn find_matches_names_tags_and_ids_ignoring_case() {
+        let mut named = saved("4611686018", Some("Main"));
+        named.display_name = Some("Guardian".to_string());
+        named.display_tag = Some(42);
+
+        let profiles = Profiles {
+            saved_profiles: vec![named, saved("4611686019", None)],
+            ..Default::default()
+        };
+
+        let found = |query| profiles.find(query).map(|p| p.profile.account_id.as_str());
+
+        assert_eq!(found("main"), Some("4611686018"));
+        assert_eq!(found("GUARDIAN"), Some("4611686018"));
+        assert_eq!(found("guardian#0042"), Some("4611686018"));
+        assert_eq!(found("4611686019"), Some("4611686019"));
+        assert_eq!(found("guardian#42"), None);
+        assert_eq!(found("461168601"), None);
+    }
+
+    #[test]
+    fn merging_keeps_existing_profiles_and_overrides() {
+        let current = Profiles {
+            saved_profiles: vec![saved("1", Some("main"))],
+            selected_profile: Some(profile("1")),
+            preference_overrides: vec![overrides("1", true)],
+        };
+
+        let imported = Profiles {
+            saved_profiles: vec![saved("1", Some("imported")), saved("2", None)],
+            selected_profile: Some(profile("2")),
+            preference_overrides: vec![overrides("1", false), overrides("2", false)],
+        };
+
+        let merged = current.merged(imported).unwrap();
+
+        let nicknames: Vec<_> = merged
+            .saved_profiles
+            .iter()
+            .map(|p| p.nickname.as_deref())
+            .collect();
+
+        assert_eq!(nicknames, [Some("main"), None]);
+        assert_eq!(merged.selected_profile, Some(profile("1")));
+        assert_eq!(merged.preference_overrides.len(), 2);
+        assert_eq!(
+            merged.overrides_for(&profile("1")).unwrap()["enableOverlay"],
+            json!(true)
+        );
+    }
+
+    #[test]
+    fn merging_takes_the_other_selection_when_there_is_none() {
+        let imported = Profiles {
+            saved_profiles: vec![saved("2", None)],
+            selected_profile: Some(profile("2")),
+            preference_overrides: Vec::new(),
+        };
+
+        let merged = Profiles::default().merged(imported).unwrap();
+
+        assert_eq!(merged.selected_profile, Some(profile("2")));
+    }
 }
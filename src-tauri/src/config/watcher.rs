@@ -0,0 +1,31 @@
+use std::fs::create_dir_all;
+
+use anyhow::Result;
+use notify::{recommended_watcher, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::ConfigKind;
+use crate::paths::data_dir;
+
+// Reports every change to a config file. Temporary and backup files are
+// ignored, the rename that moves a write into place is reported instead.
+// Watching stops once the returned watcher is dropped
+pub fn watch_config_dir(sender: UnboundedSender<ConfigKind>) -> Result<RecommendedWatcher> {
+    let dir = data_dir()?;
+    create_dir_all(dir)?;
+
+    let mut watcher = recommended_watcher(move |event: notify::Result<Event>| {
+        let event = match event {
+            Ok(e) if e.kind.is_create() || e.kind.is_modify() => e,
+            _ => return,
+        };
+
+        for kind in event.paths.iter().filter_map(|p| ConfigKind::from_path(p)) {
+            let _ = sender.send(kind);
+        }
+    })?;
+
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    Ok(watcher)
+}
@@ -1,12 +1,32 @@
 use std::time::Duration;
 
-pub const TARGET_NAME: &str = "destiny2.exe";
 pub const OVERLAY_POLL_INTERVAL: Duration = Duration::from_millis(200);
+pub const WINDOW_CACHE_SIZE: usize = 64;
+pub const OVERLAY_CORNER_MARGIN: f64 = 24.0;
+pub const OVERLAY_SHOW_AFTER_CLEAR: Duration = Duration::from_secs(60);
+// Editors and our own atomic writes touch a config file several times in a
+// row, so changes are only read once things settle down
+pub const CONFIG_RELOAD_DEBOUNCE: Duration = Duration::from_millis(250);
+// How often the cached names of saved profiles are refreshed
+pub const PROFILE_REFRESH_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+// Lower bounds for the polling preferences, so a bad config can't poll the
+// API in a tight loop
+pub const MIN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+pub const MIN_HISTORY_POLL_RATIO: usize = 2;
+pub const PROCESS_POLL_INTERVAL: Duration = Duration::from_secs(5);
 pub const APP_NAME: &str = "threepole";
 pub const APP_VER: &str = env!("CARGO_PKG_VERSION");
 pub const API_KEY: &str = env!("BUNGIE_API_KEY");
 pub const API_PATH: &str = "https://www.bungie.net/Platform";
-pub const NAMED_PIPE: &str = r"\\.\pipe\threepole-open";
+// Names the pipe or socket the running instance takes commands on
+pub const INSTANCE_NAME: &str = "threepole-open";
+pub const DATA_DIR_ENV: &str = "THREEPOLE_DATA_DIR";
+// An empty file with this name next to the executable enables portable mode
+pub const PORTABLE_MARKER: &str = "portable";
+pub const PORTABLE_DATA_DIR: &str = "data";
+pub const WEBVIEW_DATA_DIR: &str = "webview";
+pub const LOCAL_API_TOKEN_LEN: usize = 32;
+pub const BUNDLE_VERSION: usize = 1;
 pub const USER_AGENT: &str = concat!("threepole/", env!("CARGO_PKG_VERSION"));
 
 pub const RAID_ACTIVITY_MODE: usize = 4;
@@ -14,4 +34,13 @@ pub const DUNGEON_ACTIVITY_MODE: usize = 82;
 pub const STRIKE_ACTIVITY_MODE: usize = 18;
 pub const LOSTSECTOR_ACTIVITY_MODE: usize = 87;
 
+pub const TRACKED_ACTIVITY_MODES: &[usize] = &[
+    RAID_ACTIVITY_MODE,
+    DUNGEON_ACTIVITY_MODE,
+    STRIKE_ACTIVITY_MODE,
+    LOSTSECTOR_ACTIVITY_MODE,
+];
+pub const WEEKLY_ACTIVITY_MODES: &[usize] = &[RAID_ACTIVITY_MODE, DUNGEON_ACTIVITY_MODE];
+
 pub const RAID_ACTIVITY_HASH: usize = 2043403989;
+pub const ORBIT_ACTIVITY_HASH: usize = 82913930;
@@ -0,0 +1,381 @@
+use std::{
+    collections::HashSet,
+    io::{self, ErrorKind, Write},
+    sync::{Arc, Mutex as StdMutex},
+};
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::Value;
+use tauri::async_runtime;
+use tokio::{
+    io::BufReader,
+    sync::{
+        broadcast::error::RecvError,
+        mpsc,
+        watch::{self, Receiver},
+        Mutex, Notify,
+    },
+    time::sleep,
+};
+
+use crate::{
+    api::Api,
+    cli::{find_profile, selected_label},
+    config::{
+        preferences::LocalApiPreferences, watcher::watch_config_dir, ConfigKind, ConfigManager,
+    },
+    consts::{APP_NAME, APP_VER, CONFIG_RELOAD_DEBOUNCE},
+    events::EventBus,
+    instance::{read_command, write_response, InstanceCommand, InstanceServer, InstanceStatus},
+    paths::data_dir,
+    pollers::{
+        host::PollerHost,
+        playerdata::{PlayerDataStatus, PlayerState},
+        process::watch_game_process,
+    },
+    server::LocalApiServer,
+    ConfigContainer, PlayerDataPollerContainer,
+};
+
+// Runs the player data poller without the Tauri app, for servers and other
+// machines without a display
+#[derive(Clone)]
+pub struct Daemon(Arc<DaemonState>);
+
+struct DaemonState {
+    config: ConfigContainer,
+    api: Api,
+    event_bus: EventBus,
+    poller: PlayerDataPollerContainer,
+    local_api: LocalApiServer,
+    game_running: Receiver<bool>,
+    shutdown: Notify,
+    // Player data updates are only logged when the state or error changes
+    last_status: StdMutex<Option<(Option<PlayerState>, Option<String>)>>,
+}
+
+// Written to stdout alongside the player data events, one JSON object
+// per line
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum DaemonLine<'a> {
+    #[serde(rename_all = "camelCase")]
+    Started {
+        version: &'a str,
+        data_dir: String,
+        profile: Option<String>,
+    },
+    Status {
+        state: Option<PlayerState>,
+        error: Option<&'a str>,
+    },
+    #[serde(rename_all = "camelCase")]
+    CharacterSwitch {
+        character_id: &'a str,
+    },
+    Stopped {
+        reason: &'a str,
+    },
+}
+
+impl PollerHost for Daemon {
+    fn config(&self) -> &ConfigContainer {
+        &self.0.config
+    }
+
+    fn api(&self) -> &Api {
+        &self.0.api
+    }
+
+    fn event_bus(&self) -> &EventBus {
+        &self.0.event_bus
+    }
+
+    fn game_running(&self) -> Receiver<bool> {
+        self.0.game_running.clone()
+    }
+
+    fn send_data_update(&self, data: &PlayerDataStatus) {
+        let status = (data.current_state(), data.error().map(|e| e.to_string()));
+        let mut last_status = self.0.last_status.lock().unwrap();
+
+        if last_status.as_ref() == Some(&status) {
+            return;
+        }
+
+        self.print_line(&DaemonLine::Status {
+            state: status.0,
+            error: status.1.as_deref(),
+        });
+
+        *last_status = Some(status);
+    }
+
+    fn send_character_switch(&self, character_id: &str) {
+        self.print_line(&DaemonLine::CharacterSwitch { character_id });
+    }
+}
+
+pub async fn run(profile: Option<String>) -> Result<()> {
+    // Shares the instance name with the app, so the two never poll the
+    // same data directory at once
+    let server = match InstanceServer::bind().await? {
+        Some(s) => s,
+        None => bail!("{APP_NAME} is already running"),
+    };
+
+    async_runtime::set(tokio::runtime::Handle::current());
+
+    let mut config = ConfigManager::load()?;
+
+    if let Some(query) = profile.as_deref() {
+        let mut profiles = config.get_profiles().clone();
+        profiles.selected_profile = Some(find_profile(&config, Some(query))?);
+        config.set_profiles(profiles)?;
+    }
+
+    if config.get_profiles().selected_profile.is_none() {
+        bail!("No profile selected, pass --profile or pick one in the app first");
+    }
+
+    for notice in config.take_recovery_notices() {
+        eprintln!("{notice}");
+    }
+
+    let profile = selected_label(&config);
+    let (sender, receiver) = watch::channel(false);
+
+    let daemon = Daemon(Arc::new(DaemonState {
+        config: ConfigContainer(Mutex::new(config)),
+        api: Api::default(),
+        event_bus: EventBus::default(),
+        poller: PlayerDataPollerContainer::default(),
+        local_api: LocalApiServer::default(),
+        game_running: receiver,
+        shutdown: Notify::new(),
+        last_status: StdMutex::new(None),
+    }));
+
+    daemon.print_line(&DaemonLine::Started {
+        version: APP_VER,
+        data_dir: data_dir()?.display().to_string(),
+        profile,
+    });
+
+    let local_api = daemon
+        .0
+        .config
+        .0
+        .lock()
+        .await
+        .get_preferences()
+        .local_api
+        .clone();
+    daemon.apply_local_api(&local_api).await;
+
+    async_runtime::spawn(watch_game_process(daemon.clone(), sender));
+    async_runtime::spawn(event_printer(daemon.clone()));
+    async_runtime::spawn(instance_loop(daemon.clone(), server));
+    async_runtime::spawn(config_watch_loop(daemon.clone()));
+
+    daemon.reset_poller().await;
+
+    let reason = tokio::select! {
+        reason = shutdown_signal() => reason?,
+        _ = daemon.0.shutdown.notified() => "quit",
+    };
+
+    daemon.print_line(&DaemonLine::Stopped { reason });
+
+    Ok(())
+}
+
+impl Daemon {
+    async fn reset_poller(&self) {
+        self.0.poller.0.lock().await.reset(self.clone()).await;
+    }
+
+    async fn apply_local_api(&self, preferences: &LocalApiPreferences) {
+        if let Err(e) = self.0.local_api.apply(preferences, &self.0.event_bus).await {
+            eprintln!("Couldn't start the local API: {e}");
+        }
+    }
+
+    // println! panics once whatever reads stdout goes away, and with
+    // nothing left to log to the daemon stops instead
+    fn print_line<T: Serialize>(&self, line: &T) {
+        if let Err(e) = write_line(line) {
+            if e.kind() == ErrorKind::BrokenPipe {
+                self.0.shutdown.notify_one();
+            }
+        }
+    }
+
+    async fn run_command(&self, command: InstanceCommand) -> Result<Option<Value>> {
+        match command {
+            InstanceCommand::SwitchProfile { profile } => {
+                {
+                    let mut lock = self.0.config.0.lock().await;
+
+                    let mut profiles = lock.get_profiles().clone();
+                    profiles.selected_profile = Some(find_profile(&lock, Some(&profile))?);
+                    lock.set_profiles(profiles)?;
+                }
+
+                self.reset_poller().await;
+            }
+            InstanceCommand::ResetSession => self.reset_poller().await,
+            InstanceCommand::Status => {
+                return Ok(Some(serde_json::to_value(self.status().await?)?));
+            }
+            InstanceCommand::Quit => self.0.shutdown.notify_one(),
+            InstanceCommand::Activate
+            | InstanceCommand::OpenWindow { .. }
+            | InstanceCommand::ToggleOverlay => {
+                bail!("{APP_NAME} is running as a daemon, without any windows")
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn status(&self) -> Result<InstanceStatus> {
+        let profile = selected_label(&*self.0.config.0.lock().await);
+
+        let state = self
+            .0
+            .poller
+            .0
+            .lock()
+            .await
+            .get_data()
+            .and_then(|d| d.current_state())
+            .and_then(|s| serde_json::to_value(s).ok())
+            .and_then(|v| v.as_str().map(|s| s.to_string()));
+
+        Ok(InstanceStatus {
+            version: APP_VER.to_string(),
+            data_dir: data_dir()?.display().to_string(),
+            profile,
+            overlay_enabled: false,
+            state,
+        })
+    }
+}
+
+async fn instance_loop(daemon: Daemon, mut server: InstanceServer) -> Result<()> {
+    loop {
+        let mut conn = BufReader::new(server.accept().await?);
+        let daemon = daemon.clone();
+
+        async_runtime::spawn(async move {
+            let result = match read_command(&mut conn).await {
+                Ok(command) => daemon.run_command(command).await,
+                Err(e) => Err(e),
+            };
+
+            let _ = write_response(conn.get_mut(), result).await;
+        });
+    }
+}
+
+async fn event_printer(daemon: Daemon) {
+    let mut receiver = daemon.0.event_bus.subscribe();
+
+    loop {
+        match receiver.recv().await {
+            Ok(event) => daemon.print_line(&event),
+            Err(RecvError::Lagged(_)) => (),
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+// Applies config files edited outside of the daemon, e.g. by the app's
+// windows or by hand, the same way the app does
+async fn config_watch_loop(daemon: Daemon) {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+
+    // Kept alive for as long as the loop runs
+    let _watcher = match watch_config_dir(sender) {
+        Ok(w) => w,
+        Err(e) => {
+            eprintln!("Config changes need a restart to apply: {e}");
+            return;
+        }
+    };
+
+    while let Some(kind) = receiver.recv().await {
+        sleep(CONFIG_RELOAD_DEBOUNCE).await;
+
+        let mut changed = HashSet::from([kind]);
+
+        while let Ok(kind) = receiver.try_recv() {
+            changed.insert(kind);
+        }
+
+        let profile_changed = {
+            let mut lock = daemon.0.config.0.lock().await;
+            let selected = lock.get_profiles().selected_profile.clone();
+
+            // Files that fail to load keep their current values until
+            // they're saved again in a valid state
+            let mut reloaded = false;
+
+            if changed.contains(&ConfigKind::Preferences) {
+                reloaded |= matches!(lock.reload_preferences(), Ok(true));
+            }
+
+            if changed.contains(&ConfigKind::Profiles) {
+                reloaded |= matches!(lock.reload_profiles(), Ok(true));
+            }
+
+            // Profiles can carry their own preferences too
+            if reloaded {
+                daemon
+                    .apply_local_api(&lock.get_preferences().local_api)
+                    .await;
+            }
+
+            lock.get_profiles().selected_profile != selected
+        };
+
+        if profile_changed {
+            daemon.reset_poller().await;
+        }
+    }
+}
+
+fn write_line<T: Serialize>(line: &T) -> io::Result<()> {
+    let mut value = serde_json::to_value(line)?;
+
+    if let Value::Object(o) = &mut value {
+        o.insert("time".to_string(), Value::String(Utc::now().to_rfc3339()));
+    }
+
+    let mut stdout = io::stdout().lock();
+
+    writeln!(stdout, "{value}")?;
+    stdout.flush()
+}
+
+#[cfg(unix)]
+async fn shutdown_signal() -> Result<&'static str> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+
+    Ok(tokio::select! {
+        res = tokio::signal::ctrl_c() => res.map(|_| "interrupt")?,
+        _ = terminate.recv() => "terminate",
+    })
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() -> Result<&'static str> {
+    tokio::signal::ctrl_c().await?;
+
+    Ok("interrupt")
+}
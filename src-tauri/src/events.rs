@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tokio::sync::{
+    broadcast::{self, error::RecvError, Receiver, Sender},
+    watch,
+};
+
+use crate::{
+    api::responses::CompletedActivity,
+    pollers::playerdata::{CurrentActivity, PlayerDataStatus, PlayerState},
+};
+
+const EVENT_BUS_CAPACITY: usize = 64;
+
+#[derive(Serialize, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PlayerDataEvent {
+    StateChanged(StateChange),
+    ActivityStarted(CurrentActivity),
+    ActivityChanged(CurrentActivity),
+    ActivityCompleted(CompletedActivity),
+    ActivityFailed(CompletedActivity),
+    NewPersonalBest(PersonalBest),
+    DailyClearMilestone(ClearMilestone),
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StateChange {
+    pub previous: PlayerState,
+    pub state: PlayerState,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonalBest {
+    pub activity: CompletedActivity,
+    // None for the first clear of an activity
+    pub previous_best_seconds: Option<usize>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearMilestone {
+    pub clears: usize,
+}
+
+// Clones share the same channels
+#[derive(Clone)]
+pub struct EventBus {
+    events: Sender<PlayerDataEvent>,
+    // The latest player data, for consumers outside of the app's windows
+    status: Arc<watch::Sender<PlayerDataStatus>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self {
+            events: broadcast::channel(EVENT_BUS_CAPACITY).0,
+            status: Arc::new(watch::channel(PlayerDataStatus::default()).0),
+        }
+    }
+}
+
+impl EventBus {
+    pub fn send(&self, event: PlayerDataEvent) {
+        // Sending only fails when nothing is subscribed, which is fine
+        let _ = self.events.send(event);
+    }
+
+    pub fn subscribe(&self) -> Receiver<PlayerDataEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn send_status(&self, status: PlayerDataStatus) {
+        self.status.send_replace(status);
+    }
+
+    pub fn status(&self) -> PlayerDataStatus {
+        self.status.borrow().clone()
+    }
+
+    pub fn subscribe_status(&self) -> watch::Receiver<PlayerDataStatus> {
+        self.status.subscribe()
+    }
+}
+
+// Forwards every event to all open windows, so each window can pick
+// out the events it cares about
+pub async fn window_forwarder(handle: AppHandle) {
+    let mut receiver = handle.state::<EventBus>().subscribe();
+
+    loop {
+        match receiver.recv().await {
+            Ok(event) => handle.emit_all("playerdata_event", event).unwrap(),
+            Err(RecvError::Lagged(_)) => (),
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    error::Error,
+    fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
+    io,
+};
+
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+#[cfg(unix)]
+pub use self::unix::{connect, InstanceServer};
+#[cfg(windows)]
+pub use self::win32::{connect, InstanceServer};
+use crate::{
+    consts::{APP_NAME, INSTANCE_NAME},
+    paths::{data_dir, data_dir_overridden},
+};
+
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod win32;
+
+// Commands accepted by the running instance, one JSON object per line, e.g.
+// {"command":"openWindow","window":"details"}
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "command", rename_all = "camelCase")]
+pub enum InstanceCommand {
+    // What a second launch sends: shows the app
+    Activate,
+    OpenWindow { window: AppWindow },
+    // Matched against nicknames, display names and account ids of saved
+    // profiles
+    SwitchProfile { profile: String },
+    ToggleOverlay,
+    ResetSession,
+    // Answered with an InstanceStatus
+    Status,
+    Quit,
+}
+
+#[derive(Serialize, Deserialize, ValueEnum, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum AppWindow {
+    Details,
+    Preferences,
+    Profiles,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceResponse {
+    pub error: Option<String>,
+    pub data: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceStatus {
+    pub version: String,
+    pub data_dir: String,
+    pub profile: Option<String>,
+    pub overlay_enabled: bool,
+    // The player state as reported by the player data poller, e.g. "orbit"
+    pub state: Option<String>,
+}
+
+// Returned by send_command when there's no instance to connect to
+#[derive(Debug)]
+pub struct NotRunning;
+
+impl Display for NotRunning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{APP_NAME} isn't running")
+    }
+}
+
+impl Error for NotRunning {}
+
+// Instances with their own data directory run alongside the default one
+// rather than handing over to it
+fn instance_name() -> String {
+    match data_dir() {
+        Ok(dir) if data_dir_overridden() => {
+            let mut hasher = DefaultHasher::new();
+            dir.hash(&mut hasher);
+
+            format!("{INSTANCE_NAME}-{:x}", hasher.finish())
+        }
+        _ => INSTANCE_NAME.to_string(),
+    }
+}
+
+// Sends a command to the running instance and waits for it to be handled,
+// returning the data it answered with
+pub async fn send_command(command: &InstanceCommand) -> Result<Option<Value>> {
+    let mut conn = BufReader::new(connect().await.map_err(|_| NotRunning)?);
+
+    let mut line = serde_json::to_string(command)?;
+    line.push('\n');
+
+    conn.get_mut().write_all(line.as_bytes()).await?;
+
+    let mut response = String::new();
+    conn.read_line(&mut response).await?;
+
+    // Versions before commands existed close the connection without
+    // answering, which still counts as activated
+    if response.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let response: InstanceResponse = serde_json::from_str(&response)?;
+
+    match response.error {
+        Some(e) => Err(anyhow!(e)),
+        None => Ok(response.data),
+    }
+}
+
+// Reads the command sent over a connection. Clients that send nothing at all
+// are treated as a second launch
+pub async fn read_command<C: AsyncRead + Unpin>(
+    conn: &mut BufReader<C>,
+) -> Result<InstanceCommand> {
+    let mut line = String::new();
+    conn.read_line(&mut line).await?;
+
+    if line.trim().is_empty() {
+        return Ok(InstanceCommand::Activate);
+    }
+
+    Ok(serde_json::from_str(&line)?)
+}
+
+pub async fn write_response<C: AsyncWrite + Unpin>(
+    conn: &mut C,
+    result: Result<Option<Value>>,
+) -> io::Result<()> {
+    let response = match result {
+        Ok(data) => InstanceResponse { error: None, data },
+        Err(e) => InstanceResponse {
+            error: Some(e.to_string()),
+            data: None,
+        },
+    };
+
+    let mut line = serde_json::to_string(&response)?;
+    line.push('\n');
+
+    conn.write_all(line.as_bytes()).await?;
+    conn.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tokio::io::{duplex, AsyncReadExt};
+
+    use super::*;
+
+    async fn command_from(sent: &str) -> Result<InstanceCommand> {
+        let (mut client, server) = duplex(1024);
+
+        client.write_all(sent.as_bytes()).await?;
+        drop(client);
+
+        read_command(&mut BufReader::new(server)).await
+    }
+
+    async fn response_line(result: Result<Option<Value>>) -> Value {
+        let (mut client, mut server) = duplex(1024);
+
+        write_response(&mut server, result).await.unwrap();
+        drop(server);
+
+        let mut line = String::new();
+        client.read_to_string(&mut line).await.unwrap();
+
+        assert!(line.ends_with('\n'));
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[tokio::test]
+    async fn commands_are_read_one_per_line() {
+        let command = command_from("{\"command\":\"switchProfile\",\"profile\":\"main\"}\n")
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            command,
+            InstanceCommand::SwitchProfile { profile } if profile == "main"
+        ));
+
+        let command = command_from("{\"command\":\"openWindow\",\"window\":\"preferences\"}\n")
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            command,
+            InstanceCommand::OpenWindow {
+                window: AppWindow::Preferences
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn empty_connections_activate() {
+        assert!(matches!(
+            command_from("").await.unwrap(),
+            InstanceCommand::Activate
+        ));
+    }
+
+    #[tokio::test]
+    async fn unknown_commands_are_errors() {
+        assert!(command_from("{\"command\":\"explode\"}\n").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn responses_carry_data_or_an_error() {
+        assert_eq!(
+            response_line(Ok(Some(json!({ "state": "orbit" })))).await,
+            json!({ "error": null, "data": { "state": "orbit" } })
+        );
+
+        assert_eq!(
+            response_line(Err(anyhow!("No saved profile matches"))).await,
+            json!({ "error": "No saved profile matches", "data": null })
+        );
+    }
+}
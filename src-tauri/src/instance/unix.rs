@@ -0,0 +1,61 @@
+use std::{fs::remove_file, io::ErrorKind, path::PathBuf};
+
+use anyhow::Result;
+use directories::BaseDirs;
+use tokio::net::{UnixListener, UnixStream};
+
+use super::instance_name;
+use crate::paths::data_dir;
+
+pub struct InstanceServer {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl InstanceServer {
+    // Returns None when another instance is already listening
+    pub async fn bind() -> Result<Option<Self>> {
+        let path = socket_path()?;
+
+        let listener = match UnixListener::bind(&path) {
+            Ok(l) => l,
+            Err(e) if e.kind() == ErrorKind::AddrInUse => {
+                if UnixStream::connect(&path).await.is_ok() {
+                    return Ok(None);
+                }
+
+                // Left behind by an instance that didn't shut down cleanly
+                remove_file(&path)?;
+                UnixListener::bind(&path)?
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Some(Self { listener, path }))
+    }
+
+    pub async fn accept(&mut self) -> Result<UnixStream> {
+        Ok(self.listener.accept().await?.0)
+    }
+}
+
+impl Drop for InstanceServer {
+    fn drop(&mut self) {
+        let _ = remove_file(&self.path);
+    }
+}
+
+pub async fn connect() -> Result<UnixStream> {
+    Ok(UnixStream::connect(socket_path()?).await?)
+}
+
+// Sockets live in the runtime directory where there is one, as the data
+// directory may be on a filesystem that doesn't support them
+fn socket_path() -> Result<PathBuf> {
+    let dir = match BaseDirs::new().and_then(|d| d.runtime_dir().map(|p| p.to_owned())) {
+        Some(d) => d,
+        None => data_dir()?.to_owned(),
+    };
+
+    Ok(dir.join(format!("{}.sock", instance_name())))
+}
@@ -0,0 +1,59 @@
+use std::{mem, time::Duration};
+
+use anyhow::Result;
+use tokio::net::windows::named_pipe::{
+    ClientOptions, NamedPipeClient, NamedPipeServer, ServerOptions,
+};
+
+use super::instance_name;
+
+// Every pipe instance is busy serving another client
+const ERROR_PIPE_BUSY: i32 = 231;
+const PIPE_BUSY_RETRIES: usize = 20;
+
+pub struct InstanceServer {
+    server: NamedPipeServer,
+    name: String,
+}
+
+impl InstanceServer {
+    // Returns None when another instance already owns the pipe
+    pub async fn bind() -> Result<Option<Self>> {
+        let name = pipe_name();
+
+        match ServerOptions::new().first_pipe_instance(true).create(&name) {
+            Ok(server) => Ok(Some(Self { server, name })),
+            Err(_) => Ok(None),
+        }
+    }
+
+    // A new pipe instance is created for the next client before the
+    // connected one is handed out
+    pub async fn accept(&mut self) -> Result<NamedPipeServer> {
+        self.server.connect().await?;
+
+        let next = ServerOptions::new().create(&self.name)?;
+
+        Ok(mem::replace(&mut self.server, next))
+    }
+}
+
+pub async fn connect() -> Result<NamedPipeClient> {
+    let name = pipe_name();
+    let mut retries = 0;
+
+    loop {
+        match ClientOptions::new().open(&name) {
+            Ok(c) => return Ok(c),
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) && retries < PIPE_BUSY_RETRIES => {
+                retries += 1;
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+fn pipe_name() -> String {
+    format!(r"\\.\pipe\{}", instance_name())
+}
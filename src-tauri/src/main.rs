@@ -3,36 +3,70 @@
     windows_subsystem = "windows"
 )]
 
-use std::io;
+use std::{
+    collections::HashSet,
+    fs::{read_to_string, write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 use api::{
     responses::{ActivityInfo, BungieProfile, ProfileInfo},
     Api, Source,
 };
+use checklist::{get_weekly_checklist as fetch_weekly_checklist, WeeklyChecklist};
+use clap::Parser;
+use cli::{find_profile, selected_label, Cli, Command};
 use config::{
-    preferences::Preferences,
+    bundle::ImportMode,
+    preferences::{Preferences, WidgetsPreferences},
     profiles::{Profile, Profiles},
-    ConfigManager,
+    watcher::watch_config_dir,
+    ConfigKind, ConfigManager,
+};
+use consts::{APP_NAME, APP_VER, CONFIG_RELOAD_DEBOUNCE};
+use events::{window_forwarder, EventBus};
+use instance::{
+    read_command, write_response, AppWindow, InstanceCommand, InstanceServer, InstanceStatus,
 };
-use consts::{APP_NAME, APP_VER, NAMED_PIPE};
+use paths::{data_dir, init_data_dir, webview_data_dir};
 use pollers::{
     overlay::overlay_poller,
     playerdata::{PlayerDataPoller, PlayerDataStatus},
+    process::spawn_process_watcher,
+    profiles::profile_refresher,
 };
+use server::LocalApiServer;
 use tauri::{
+    api::dialog,
     async_runtime::{self, JoinHandle},
-    AppHandle, CustomMenuItem, Manager, RunEvent, State, SystemTray, SystemTrayEvent,
-    SystemTrayMenu, SystemTrayMenuItem, WindowBuilder, WindowUrl,
+    AppHandle, Manager, RunEvent, State, SystemTray, SystemTrayEvent, Window, WindowBuilder,
+    WindowUrl,
 };
 use tokio::{
-    net::windows::named_pipe::{ClientOptions, NamedPipeServer, ServerOptions},
-    sync::Mutex,
+    io::BufReader,
+    sync::{mpsc, Mutex, Notify},
+    time::sleep,
 };
+use tray::{profile_from_item_id, tray_menu, update_tray};
+use widgets::{create_widget_window, widget_windows, WidgetKind};
 
 mod api;
+mod checklist;
+mod cli;
 mod config;
 mod consts;
+mod daemon;
+mod events;
+mod instance;
+mod paths;
 mod pollers;
+mod server;
+mod tray;
+mod widgets;
 
 struct ConfigContainer(Mutex<ConfigManager>);
 
@@ -40,7 +74,19 @@ struct ConfigContainer(Mutex<ConfigManager>);
 struct PlayerDataPollerContainer(Mutex<PlayerDataPoller>);
 
 #[derive(Default)]
-struct OverlayPollerHandle(Mutex<Option<JoinHandle<()>>>);
+struct OverlayPollerHandle(Mutex<Option<OverlayPoller>>);
+
+struct OverlayPoller {
+    task: JoinHandle<()>,
+    // The poller is only restarted when the widgets change, other
+    // preferences are picked up once it's woken up
+    widgets: WidgetsPreferences,
+    preferences_changed: Arc<Notify>,
+}
+
+// Keeps the overlay closed for this session regardless of preferences, until
+// it's toggled back on
+struct OverlaySuppressed(AtomicBool);
 
 // https://github.com/tauri-apps/wry/issues/583
 #[tauri::command]
@@ -64,24 +110,33 @@ async fn set_preferences(
     handle: AppHandle,
     preferences: Preferences,
     container: State<'_, ConfigContainer>,
-    poller_handle: State<'_, OverlayPollerHandle>,
-) -> Result<(), ()> {
+) -> Result<(), String> {
     let mut lock = container.0.lock().await;
-    lock.set_preferences(preferences.clone()).unwrap();
-
-    if let Some(o) = handle.get_window("overlay") {
-        if preferences.enable_overlay {
-            o.emit("preferences_update", preferences).unwrap();
-        } else {
-            if let Some(h) = poller_handle.0.lock().await.as_ref() {
-                h.abort();
-            }
+    lock.set_preferences(preferences)
+        .map_err(|e| e.to_string())?;
 
-            o.close().unwrap();
-        }
-    } else if preferences.enable_overlay {
-        create_overlay(handle).await.unwrap();
-    }
+    apply_preferences(&handle, lock.get_preferences()).await;
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_global_preferences(container: State<'_, ConfigContainer>) -> Result<Preferences, ()> {
+    Ok(container.0.lock().await.get_global_preferences().clone())
+}
+
+// Saves preferences for the selected profile only
+#[tauri::command]
+async fn set_profile_preferences(
+    handle: AppHandle,
+    preferences: Preferences,
+    container: State<'_, ConfigContainer>,
+) -> Result<(), String> {
+    let mut lock = container.0.lock().await;
+    lock.set_profile_preferences(preferences)
+        .map_err(|e| e.to_string())?;
+
+    apply_preferences(&handle, lock.get_preferences()).await;
 
     Ok(())
 }
@@ -96,23 +151,47 @@ async fn set_profiles(
     handle: AppHandle,
     profiles: Profiles,
     config_container: State<'_, ConfigContainer>,
-    poller_container: State<'_, PlayerDataPollerContainer>,
-) -> Result<(), ()> {
+) -> Result<(), String> {
     let mut lock = config_container.0.lock().await;
 
-    let was_no_profile = lock.get_profiles().selected_profile.is_none();
+    let previous = SelectedProfile::of(&lock);
 
-    lock.set_profiles(profiles).unwrap();
+    lock.set_profiles(profiles).map_err(|e| e.to_string())?;
 
-    if was_no_profile {
-        if handle.get_window("overlay").is_none() && lock.get_preferences().enable_overlay {
-            create_overlay(handle.clone()).await.unwrap();
-        }
+    apply_profiles(&handle, &lock, previous).await;
 
-        open_details_window(&handle, true).unwrap();
-    }
+    Ok(())
+}
 
-    poller_container.0.lock().await.reset(handle).await;
+#[tauri::command]
+async fn export_config(path: PathBuf, container: State<'_, ConfigContainer>) -> Result<(), String> {
+    let contents = container
+        .0
+        .lock()
+        .await
+        .export_bundle()
+        .map_err(|e| e.to_string())?;
+
+    write(path, contents).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn import_config(
+    handle: AppHandle,
+    path: PathBuf,
+    mode: ImportMode,
+    container: State<'_, ConfigContainer>,
+) -> Result<(), String> {
+    let contents = read_to_string(path).map_err(|e| e.to_string())?;
+
+    let mut lock = container.0.lock().await;
+
+    let previous = SelectedProfile::of(&lock);
+
+    lock.import_bundle(&contents, mode)
+        .map_err(|e| e.to_string())?;
+
+    apply_profiles(&handle, &lock, previous).await;
 
     Ok(())
 }
@@ -152,40 +231,214 @@ async fn search_profile(
         .map_err(|e| e.to_string())?)
 }
 
-async fn create_overlay(handle: AppHandle) -> Result<(), tauri::Error> {
-    let overlay = WindowBuilder::new(
-        &handle,
-        "overlay",
-        WindowUrl::App("./src/overlay/overlay.html".into()),
-    )
-    .title(APP_NAME)
-    .transparent(true)
-    .decorations(false)
-    .inner_size(400.0, 500.0)
-    .resizable(false)
-    .always_on_top(true)
-    .inner_size(0.0, 0.0)
-    .position(0.0, 0.0)
-    .visible(false)
-    .skip_taskbar(true)
-    .build()?;
+#[tauri::command]
+async fn get_weekly_checklist(
+    config_container: State<'_, ConfigContainer>,
+    api: State<'_, Api>,
+) -> Result<WeeklyChecklist, String> {
+    let profile = match &config_container
+        .0
+        .lock()
+        .await
+        .get_profiles()
+        .selected_profile
+    {
+        Some(p) => p.clone(),
+        None => return Err("No profile set".to_string()),
+    };
+
+    fetch_weekly_checklist(&api, &profile)
+        .await
+        .map_err(|e| e.to_string())
+}
 
-    overlay.set_ignore_cursor_events(true).unwrap();
+async fn apply_preferences(handle: &AppHandle, preferences: &Preferences) {
+    apply_local_api(handle, preferences).await;
 
-    #[cfg(debug_assertions)]
-    overlay.open_devtools();
+    if overlay_enabled(handle, preferences) {
+        create_overlay(handle.clone(), &preferences.widgets)
+            .await
+            .unwrap();
 
-    let handle_clone = handle.clone();
+        for (_, w) in widget_windows(handle) {
+            w.emit("preferences_update", preferences.clone()).unwrap();
+        }
+    } else {
+        if let Some(p) = handle.state::<OverlayPollerHandle>().0.lock().await.take() {
+            p.task.abort();
+        }
+
+        for (_, w) in widget_windows(handle) {
+            w.close().unwrap();
+        }
+    }
+}
+
+async fn apply_local_api(handle: &AppHandle, preferences: &Preferences) {
+    let bus = handle.state::<EventBus>();
+
+    if let Err(e) = handle
+        .state::<LocalApiServer>()
+        .apply(&preferences.local_api, &bus)
+        .await
+    {
+        dialog::message(
+            None::<&Window>,
+            APP_NAME,
+            format!("Couldn't start the local API: {e}"),
+        );
+    }
+}
+
+fn overlay_enabled(handle: &AppHandle, preferences: &Preferences) -> bool {
+    preferences.enable_overlay
+        && !handle
+            .state::<OverlaySuppressed>()
+            .0
+            .load(Ordering::Relaxed)
+}
+
+// What the player data poller tracks, taken before the profiles change so
+// edits to nicknames, pins and other details don't reset it
+#[derive(PartialEq)]
+struct SelectedProfile {
+    profile: Option<Profile>,
+    overrides: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+impl SelectedProfile {
+    fn of(config: &ConfigManager) -> Self {
+        let profiles = config.get_profiles();
+
+        Self {
+            profile: profiles.selected_profile.clone(),
+            overrides: profiles.overrides_for_selected().cloned(),
+        }
+    }
+}
+
+// Profiles can carry their own preferences, so those are applied again too
+async fn apply_profiles(handle: &AppHandle, config: &ConfigManager, previous: SelectedProfile) {
+    update_tray(handle, config.get_profiles());
+    apply_preferences(handle, config.get_preferences()).await;
+
+    if previous.profile.is_none() {
+        open_details_window(handle, true).unwrap();
+    }
+
+    if SelectedProfile::of(config) != previous {
+        let poller_container = handle.state::<PlayerDataPollerContainer>();
+        poller_container.0.lock().await.reset(handle.clone()).await;
+    }
+}
+
+async fn switch_profile(handle: &AppHandle, profile: Profile) -> anyhow::Result<()> {
+    let config_container = handle.state::<ConfigContainer>();
+    let mut lock = config_container.0.lock().await;
+
+    let mut profiles = lock.get_profiles().clone();
+
+    if profiles.selected_profile.as_ref() == Some(&profile) {
+        // Some platforms toggle the check mark on click, so the menu is
+        // rebuilt even if nothing changed
+        update_tray(handle, &profiles);
+        return Ok(());
+    }
+
+    profiles.selected_profile = Some(profile);
+
+    let previous = SelectedProfile::of(&lock);
+
+    lock.set_profiles(profiles)?;
+
+    apply_profiles(handle, &lock, previous).await;
+
+    Ok(())
+}
+
+// Applies config files edited outside of the app the same way as changes
+// made through the preferences and profiles windows
+async fn config_watch_loop(handle: AppHandle) {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+
+    // Kept alive for as long as the loop runs
+    let _watcher = match watch_config_dir(sender) {
+        Ok(w) => w,
+        Err(_) => return,
+    };
+
+    while let Some(kind) = receiver.recv().await {
+        sleep(CONFIG_RELOAD_DEBOUNCE).await;
+
+        let mut changed = HashSet::from([kind]);
+
+        while let Ok(kind) = receiver.try_recv() {
+            changed.insert(kind);
+        }
+
+        let config_container = handle.state::<ConfigContainer>();
+        let mut lock = config_container.0.lock().await;
+
+        // Files that fail to load keep their current values until they're
+        // saved again in a valid state
+        if changed.contains(&ConfigKind::Preferences) {
+            if let Ok(true) = lock.reload_preferences() {
+                apply_preferences(&handle, lock.get_preferences()).await;
+            }
+        }
+
+        if changed.contains(&ConfigKind::Profiles) {
+            let previous = SelectedProfile::of(&lock);
+
+            if let Ok(true) = lock.reload_profiles() {
+                apply_profiles(&handle, &lock, previous).await;
+            }
+        }
+    }
+}
+
+// Opens the windows of enabled widgets and closes those of disabled ones,
+// then restarts the overlay poller to manage them. Nothing is recreated if
+// the widgets are unchanged, so saving other preferences doesn't flicker
+async fn create_overlay(
+    handle: AppHandle,
+    widgets: &WidgetsPreferences,
+) -> Result<(), tauri::Error> {
     let poller_handle = handle.state::<OverlayPollerHandle>();
     let mut lock = poller_handle.0.lock().await;
 
-    if let Some(h) = lock.as_ref() {
-        h.abort();
+    if let Some(p) = lock.as_ref().filter(|p| &p.widgets == widgets) {
+        p.preferences_changed.notify_one();
+        return Ok(());
+    }
+
+    for kind in WidgetKind::ALL {
+        let enabled = kind.preferences(widgets).enabled;
+
+        match handle.get_window(kind.label()) {
+            Some(w) if !enabled => w.close()?,
+            None if enabled => {
+                create_widget_window(&handle, kind)?;
+            }
+            _ => (),
+        }
     }
 
-    let handle = async_runtime::spawn(async move { overlay_poller(handle_clone).await });
+    if let Some(p) = lock.take() {
+        p.task.abort();
+    }
 
-    *lock = Some(handle);
+    let preferences_changed = Arc::new(Notify::new());
+
+    let handle_clone = handle.clone();
+    let notify = preferences_changed.clone();
+    let task = async_runtime::spawn(async move { overlay_poller(handle_clone, notify).await });
+
+    *lock = Some(OverlayPoller {
+        task,
+        widgets: widgets.clone(),
+        preferences_changed,
+    });
 
     Ok(())
 }
@@ -203,7 +456,7 @@ fn open_preferences_window(handle: &AppHandle) -> Result<(), tauri::Error> {
         return w.set_focus();
     }
 
-    WindowBuilder::new(
+    let mut builder = WindowBuilder::new(
         handle,
         "preferences",
         WindowUrl::App("./src/window/window.html#preferences".into()),
@@ -212,8 +465,13 @@ fn open_preferences_window(handle: &AppHandle) -> Result<(), tauri::Error> {
     .decorations(false)
     .inner_size(400.0, 500.0)
     .resizable(false)
-    .visible(false)
-    .build()?;
+    .visible(false);
+
+    if let Some(dir) = webview_data_dir() {
+        builder = builder.data_directory(dir);
+    }
+
+    builder.build()?;
 
     Ok(())
 }
@@ -224,7 +482,7 @@ fn open_profiles_window(handle: &AppHandle) -> Result<(), tauri::Error> {
         return w.set_focus();
     }
 
-    WindowBuilder::new(
+    let mut builder = WindowBuilder::new(
         handle,
         "profiles",
         WindowUrl::App("./src/window/window.html#profiles".into()),
@@ -233,8 +491,13 @@ fn open_profiles_window(handle: &AppHandle) -> Result<(), tauri::Error> {
     .decorations(false)
     .inner_size(400.0, 500.0)
     .resizable(false)
-    .visible(false)
-    .build()?;
+    .visible(false);
+
+    if let Some(dir) = webview_data_dir() {
+        builder = builder.data_directory(dir);
+    }
+
+    builder.build()?;
 
     Ok(())
 }
@@ -245,7 +508,7 @@ fn open_details_window(handle: &AppHandle, welcome: bool) -> Result<(), tauri::E
         return w.set_focus();
     }
 
-    WindowBuilder::new(
+    let mut builder = WindowBuilder::new(
         handle,
         "details",
         WindowUrl::App(
@@ -260,8 +523,13 @@ fn open_details_window(handle: &AppHandle, welcome: bool) -> Result<(), tauri::E
     .decorations(false)
     .inner_size(600.0, 600.0)
     .resizable(false)
-    .visible(false)
-    .build()?;
+    .visible(false);
+
+    if let Some(dir) = webview_data_dir() {
+        builder = builder.data_directory(dir);
+    }
+
+    builder.build()?;
 
     Ok(())
 }
@@ -277,57 +545,203 @@ async fn activate(handle: &AppHandle) -> Result<(), tauri::Error> {
     }
 }
 
-async fn pipe_loop(handle: AppHandle, mut pipe_server: NamedPipeServer) -> io::Result<()> {
+async fn instance_loop(handle: AppHandle, mut server: InstanceServer) -> anyhow::Result<()> {
     loop {
-        pipe_server.connect().await?;
-        pipe_server = ServerOptions::new().create(NAMED_PIPE)?;
-        pipe_server.disconnect()?;
+        let mut conn = BufReader::new(server.accept().await?);
+        let handle = handle.clone();
 
-        activate(&handle).await.unwrap();
+        async_runtime::spawn(async move {
+            let result = match read_command(&mut conn).await {
+                Ok(command) => run_command(&handle, command).await,
+                Err(e) => Err(e),
+            };
+
+            let _ = write_response(conn.get_mut(), result).await;
+        });
     }
 }
 
+async fn run_command(
+    handle: &AppHandle,
+    command: InstanceCommand,
+) -> anyhow::Result<Option<serde_json::Value>> {
+    match command {
+        InstanceCommand::Activate => activate(handle).await?,
+        InstanceCommand::OpenWindow { window } => open_app_window(handle, window)?,
+        InstanceCommand::SwitchProfile { profile } => {
+            let profile = {
+                let config_container = handle.state::<ConfigContainer>();
+                let lock = config_container.0.lock().await;
+
+                match lock.get_profiles().find(&profile) {
+                    Some(p) => p.profile.clone(),
+                    None => anyhow::bail!("No saved profile matches \"{profile}\""),
+                }
+            };
+
+            switch_profile(handle, profile).await?;
+        }
+        InstanceCommand::ToggleOverlay => toggle_overlay(handle).await?,
+        InstanceCommand::ResetSession => {
+            let poller_container = handle.state::<PlayerDataPollerContainer>();
+            poller_container.0.lock().await.reset(handle.clone()).await;
+        }
+        InstanceCommand::Status => {
+            return Ok(Some(serde_json::to_value(instance_status(handle).await?)?));
+        }
+        InstanceCommand::Quit => handle.exit(0),
+    }
+
+    Ok(None)
+}
+
+async fn instance_status(handle: &AppHandle) -> anyhow::Result<InstanceStatus> {
+    let (profile, overlay_enabled) = {
+        let config_container = handle.state::<ConfigContainer>();
+        let lock = config_container.0.lock().await;
+
+        (
+            selected_label(&lock),
+            overlay_enabled(handle, lock.get_preferences()),
+        )
+    };
+
+    let state = handle
+        .state::<PlayerDataPollerContainer>()
+        .0
+        .lock()
+        .await
+        .get_data()
+        .and_then(|d| d.current_state())
+        .and_then(|s| serde_json::to_value(s).ok())
+        .and_then(|v| v.as_str().map(|s| s.to_string()));
+
+    Ok(InstanceStatus {
+        version: APP_VER.to_string(),
+        data_dir: data_dir()?.display().to_string(),
+        profile,
+        overlay_enabled,
+        state,
+    })
+}
+
+fn open_app_window(handle: &AppHandle, window: AppWindow) -> Result<(), tauri::Error> {
+    match window {
+        AppWindow::Details => open_details_window(handle, false),
+        AppWindow::Preferences => open_preferences_window(handle),
+        AppWindow::Profiles => open_profiles_window(handle),
+    }
+}
+
+// Flips the overlay for the selected profile, where it's overridden there,
+// or else for all profiles
+async fn toggle_overlay(handle: &AppHandle) -> anyhow::Result<()> {
+    let config_container = handle.state::<ConfigContainer>();
+    let mut lock = config_container.0.lock().await;
+
+    let suppressed = handle
+        .state::<OverlaySuppressed>()
+        .0
+        .swap(false, Ordering::Relaxed);
+
+    // Only hidden for this session, so showing it again is enough
+    if suppressed && lock.get_preferences().enable_overlay {
+        apply_preferences(handle, lock.get_preferences()).await;
+        return Ok(());
+    }
+
+    let enable_overlay = !lock.get_preferences().enable_overlay;
+
+    let overridden = lock
+        .get_profiles()
+        .overrides_for_selected()
+        .map_or(false, |o| o.contains_key("enableOverlay"));
+
+    if overridden {
+        let mut preferences = lock.get_preferences().clone();
+        preferences.enable_overlay = enable_overlay;
+        lock.set_profile_preferences(preferences)?;
+    } else {
+        let mut preferences = lock.get_global_preferences().clone();
+        preferences.enable_overlay = enable_overlay;
+        lock.set_preferences(preferences)?;
+    }
+
+    apply_preferences(handle, lock.get_preferences()).await;
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let pipe_server = match ServerOptions::new()
-        .first_pipe_instance(true)
-        .create(NAMED_PIPE)
-    {
-        Ok(s) => s,
-        Err(_) => {
-            ClientOptions::new().open(NAMED_PIPE)?;
-            return Ok(());
-        }
+    let mut cli = Cli::parse();
+
+    init_data_dir(cli.data_dir.clone(), cli.portable)?;
+
+    // Headless launches are the daemon, without any windows, tray or overlay
+    let command = cli
+        .command
+        .take()
+        .or_else(|| cli.headless.then(|| Command::Daemon));
+
+    if let Some(command) = command {
+        return cli::run(command, cli.profile).await;
+    }
+
+    let instance_server = match InstanceServer::bind().await? {
+        Some(s) => s,
+        None => return cli.send_launch_commands().await,
     };
 
     tauri::async_runtime::set(tokio::runtime::Handle::current());
 
+    let mut config = ConfigManager::load()?;
+
+    if let Some(query) = cli.profile.as_deref() {
+        let mut profiles = config.get_profiles().clone();
+        profiles.selected_profile = Some(find_profile(&config, Some(query))?);
+        config.set_profiles(profiles)?;
+    }
+
+    let menu = tray_menu(config.get_profiles());
+    let open = cli.open;
+
     tauri::Builder::new()
-        .manage(ConfigContainer(Mutex::new(ConfigManager::load()?)))
+        .manage(ConfigContainer(Mutex::new(config)))
         .manage(Api::default())
+        .manage(EventBus::default())
         .manage(PlayerDataPollerContainer::default())
         .manage(OverlayPollerHandle::default())
-        .system_tray(
-            SystemTray::new().with_menu(
-                SystemTrayMenu::new()
-                    .add_item(
-                        CustomMenuItem::new("version_info", format!("{APP_NAME} v{}", APP_VER))
-                            .disabled(),
-                    )
-                    .add_native_item(SystemTrayMenuItem::Separator)
-                    .add_item(CustomMenuItem::new("preferences", "Preferences"))
-                    .add_item(CustomMenuItem::new("set_profile", "Set profile"))
-                    .add_native_item(SystemTrayMenuItem::Separator)
-                    .add_item(CustomMenuItem::new("exit", "Exit")),
-            ),
-        )
+        .manage(LocalApiServer::default())
+        .manage(OverlaySuppressed(AtomicBool::new(cli.no_overlay)))
+        .system_tray(SystemTray::new().with_menu(menu))
         .on_system_tray_event(|handle, event| {
             if let SystemTrayEvent::MenuItemClick { id, .. } = event {
                 match id.as_str() {
                     "exit" => handle.exit(0),
                     "set_profile" => open_profiles_window(&handle).unwrap(),
                     "preferences" => open_preferences_window(&handle).unwrap(),
-                    _ => (),
+                    _ => {
+                        let handle = handle.clone();
+
+                        async_runtime::spawn(async move {
+                            let profile = {
+                                let config_container = handle.state::<ConfigContainer>();
+                                let lock = config_container.0.lock().await;
+
+                                profile_from_item_id(lock.get_profiles(), &id)
+                            };
+
+                            let profile = match profile {
+                                Some(p) => p,
+                                None => return,
+                            };
+
+                            if let Err(e) = switch_profile(&handle, profile).await {
+                                dialog::message(None::<&Window>, APP_NAME, e.to_string());
+                            }
+                        });
+                    }
                 }
             } else if let SystemTrayEvent::LeftClick { .. } = event {
                 let handle_clone = handle.clone();
@@ -339,31 +753,49 @@ async fn main() -> anyhow::Result<()> {
             open_profiles,
             get_preferences,
             set_preferences,
+            get_global_preferences,
+            set_profile_preferences,
             get_profiles,
             set_profiles,
+            export_config,
+            import_config,
             get_profile_info,
             get_activity_info,
             search_profile,
             get_playerdata,
+            get_weekly_checklist,
         ])
-        .setup(|app| {
+        .setup(move |app| {
             let handle = app.handle();
-            let pipe_handle = handle.clone();
+            app.manage(spawn_process_watcher(handle.clone()));
 
-            async_runtime::spawn(async move { pipe_loop(pipe_handle, pipe_server).await });
+            async_runtime::spawn(instance_loop(handle.clone(), instance_server));
+            async_runtime::spawn(window_forwarder(handle.clone()));
+            async_runtime::spawn(config_watch_loop(handle.clone()));
+            async_runtime::spawn(profile_refresher(handle.clone()));
 
             async_runtime::spawn(async move {
                 let config_container = handle.state::<ConfigContainer>();
-                let lock = config_container.0.lock().await;
+                let mut lock = config_container.0.lock().await;
 
-                if lock.get_profiles().selected_profile.is_none() {
-                    open_profiles_window(&handle).unwrap();
-                } else {
-                    if lock.get_preferences().enable_overlay {
-                        create_overlay(handle.clone()).await.unwrap();
-                    }
+                for notice in lock.take_recovery_notices() {
+                    dialog::message(None::<&Window>, APP_NAME, notice);
+                }
+
+                apply_local_api(&handle, lock.get_preferences()).await;
+
+                let has_profile = lock.get_profiles().selected_profile.is_some();
+
+                if has_profile && overlay_enabled(&handle, lock.get_preferences()) {
+                    create_overlay(handle.clone(), &lock.get_preferences().widgets)
+                        .await
+                        .unwrap();
+                }
 
-                    open_details_window(&handle, false).unwrap();
+                match open {
+                    Some(window) => open_app_window(&handle, window).unwrap(),
+                    None if has_profile => open_details_window(&handle, false).unwrap(),
+                    None => open_profiles_window(&handle).unwrap(),
                 }
 
                 let poller_container = handle.state::<PlayerDataPollerContainer>();
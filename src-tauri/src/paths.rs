@@ -0,0 +1,91 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use directories::BaseDirs;
+use once_cell::sync::OnceCell;
+
+use crate::consts::{APP_NAME, DATA_DIR_ENV, PORTABLE_DATA_DIR, PORTABLE_MARKER, WEBVIEW_DATA_DIR};
+
+static DATA_DIR: OnceCell<DataDir> = OnceCell::new();
+
+struct DataDir {
+    path: PathBuf,
+    overridden: bool,
+}
+
+// Picks the data directory from the command line, and must run before it's
+// first used. Resolved from the first of: the --data-dir flag, the
+// THREEPOLE_DATA_DIR variable, portable mode (the --portable flag or a marker
+// file next to the executable), and finally the user's data directory
+pub fn init_data_dir(custom: Option<PathBuf>, portable: bool) -> Result<()> {
+    let resolved = resolve(custom, portable)?;
+
+    DATA_DIR
+        .set(resolved)
+        .map_err(|_| anyhow!("Data directory already in use"))
+}
+
+// Where config and any other app data lives
+pub fn data_dir() -> Result<&'static Path> {
+    Ok(&get()?.path)
+}
+
+// Whether the data directory isn't the per-user default, in which case this
+// instance is kept separate from others
+pub fn data_dir_overridden() -> bool {
+    get().map(|d| d.overridden).unwrap_or(false)
+}
+
+// Where windows keep their caches and storage. None leaves them in the
+// WebView's per-user default, which an overridden data directory mustn't
+// share
+pub fn webview_data_dir() -> Option<PathBuf> {
+    if !data_dir_overridden() {
+        return None;
+    }
+
+    data_dir().ok().map(|d| d.join(WEBVIEW_DATA_DIR))
+}
+
+fn get() -> Result<&'static DataDir> {
+    DATA_DIR.get_or_try_init(|| resolve(None, false))
+}
+
+fn resolve(custom: Option<PathBuf>, portable: bool) -> Result<DataDir> {
+    let custom = custom.or_else(|| {
+        env::var_os(DATA_DIR_ENV)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    });
+
+    if let Some(path) = custom {
+        // Relative paths are resolved now, in case the working directory
+        // changes later on
+        return Ok(DataDir {
+            path: env::current_dir()?.join(path),
+            overridden: true,
+        });
+    }
+
+    let exe_dir = env::current_exe()?
+        .parent()
+        .map(Path::to_owned)
+        .ok_or(anyhow!("Executable has no parent directory"))?;
+
+    if portable || exe_dir.join(PORTABLE_MARKER).exists() {
+        return Ok(DataDir {
+            path: exe_dir.join(PORTABLE_DATA_DIR),
+            overridden: true,
+        });
+    }
+
+    BaseDirs::new()
+        .map(|d| DataDir {
+            path: d.data_dir().join(APP_NAME),
+            overridden: false,
+        })
+        .ok_or(anyhow!("No data_dir available"))
+}
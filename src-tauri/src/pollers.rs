@@ -1,2 +1,7 @@
+pub mod host;
 pub mod overlay;
 pub mod playerdata;
+pub mod process;
+pub mod profiles;
+pub mod schedule;
+pub mod tracker;
@@ -0,0 +1,61 @@
+use tauri::{AppHandle, Manager};
+use tokio::sync::watch::Receiver;
+
+use super::{playerdata::PlayerDataStatus, process::GameProcessState};
+use crate::{api::Api, events::EventBus, widgets::widget_windows, ConfigContainer};
+
+// Everything the pollers need from whatever is running them, so they can
+// run in the app as well as in the headless daemon
+pub trait PollerHost: Clone + Send + Sync + 'static {
+    fn config(&self) -> &ConfigContainer;
+
+    fn api(&self) -> &Api;
+
+    fn event_bus(&self) -> &EventBus;
+
+    fn game_running(&self) -> Receiver<bool>;
+
+    fn send_data_update(&self, data: &PlayerDataStatus);
+
+    fn send_character_switch(&self, character_id: &str);
+}
+
+impl PollerHost for AppHandle {
+    fn config(&self) -> &ConfigContainer {
+        self.state::<ConfigContainer>().inner()
+    }
+
+    fn api(&self) -> &Api {
+        self.state::<Api>().inner()
+    }
+
+    fn event_bus(&self) -> &EventBus {
+        self.state::<EventBus>().inner()
+    }
+
+    fn game_running(&self) -> Receiver<bool> {
+        self.state::<GameProcessState>().0.clone()
+    }
+
+    fn send_data_update(&self, data: &PlayerDataStatus) {
+        for (_, w) in widget_windows(self) {
+            w.emit("playerdata_update", data.clone()).unwrap();
+        }
+
+        if let Some(o) = self.get_window("details") {
+            o.emit("playerdata_update", data.clone()).unwrap();
+        }
+    }
+
+    fn send_character_switch(&self, character_id: &str) {
+        for (_, w) in widget_windows(self) {
+            w.emit("character_switch", character_id.to_string())
+                .unwrap();
+        }
+
+        if let Some(o) = self.get_window("details") {
+            o.emit("character_switch", character_id.to_string())
+                .unwrap();
+        }
+    }
+}
@@ -1,148 +1,108 @@
-use std::{collections::HashMap, path::PathBuf};
-
-use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
-use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize};
-use widestring::Utf16String;
-use windows::Win32::{
-    Foundation::{HWND, MAX_PATH, RECT},
-    System::{
-        ProcessStatus::K32GetModuleFileNameExW,
-        Threading::{OpenProcess, PROCESS_QUERY_INFORMATION},
-    },
-    UI::{
-        Shell::SHQueryUserNotificationState,
-        WindowsAndMessaging::{GetForegroundWindow, GetWindowRect, GetWindowThreadProcessId},
-    },
-};
-
-use crate::consts::{OVERLAY_POLL_INTERVAL, TARGET_NAME};
+use std::{sync::Arc, time::Instant};
 
-enum PollResult {
-    Open(HWND),
-    Closed,
-    Retain,
-}
-
-#[derive(Default)]
-struct Poller {
-    hwnd_names: HashMap<isize, String>,
-}
+use tauri::{AppHandle, Manager, Window};
+use tokio::sync::Notify;
 
-impl Poller {
-    async fn poll(&mut self, overlay_hwnd: isize) -> PollResult {
-        let notification_state = unsafe { SHQueryUserNotificationState() };
+use self::{
+    layout::{monitor_scale_factor, WidgetLayout},
+    visibility::VisibilityTracker,
+};
+use super::tracker::{create_tracker, PollResult};
+use crate::{consts::OVERLAY_POLL_INTERVAL, widgets::widget_windows, ConfigContainer};
 
-        match notification_state {
-            Ok(n) if n.0 == 3 => return PollResult::Closed, // If in DX exclusive fullscreen mode
-            _ => (),
-        }
+mod layout;
+mod visibility;
 
-        let foreground_hwnd = unsafe { GetForegroundWindow() };
+pub async fn overlay_poller(handle: AppHandle, preferences_changed: Arc<Notify>) {
+    let mut tracker = match create_tracker() {
+        Some(t) => t,
+        None => return,
+    };
 
-        if overlay_hwnd == foreground_hwnd.0 {
-            return PollResult::Retain;
-        }
+    let mut visibility = VisibilityTracker::new(&handle);
+    let mut changes = tracker.watch();
 
-        let focused_name = match self.hwnd_names.get(&foreground_hwnd.0) {
-            Some(n) => n,
-            None => match get_hwnd_exec(foreground_hwnd) {
-                Some(n) => {
-                    self.hwnd_names.insert(foreground_hwnd.0, n);
-                    self.hwnd_names.get(&foreground_hwnd.0).unwrap()
-                }
-                None => return PollResult::Closed,
-            },
-        };
+    loop {
+        let widgets = widget_windows(&handle);
 
-        if focused_name == TARGET_NAME {
-            PollResult::Open(foreground_hwnd)
-        } else {
-            PollResult::Closed
+        if widgets.is_empty() {
+            return;
         }
-    }
-}
-
-fn get_hwnd_exec(hwnd: HWND) -> Option<String> {
-    if hwnd.0 == 0 {
-        return None;
-    }
-
-    let mut process_id = 0;
-
-    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut process_id)) };
-
-    if process_id == 0 {
-        return None;
-    }
 
-    let handle_res = unsafe { OpenProcess(PROCESS_QUERY_INFORMATION, false, process_id) };
+        let windows: Vec<Window> = widgets.iter().map(|(_, w)| w.clone()).collect();
 
-    let h = match handle_res {
-        Ok(h) => h,
-        Err(_) => return None,
-    };
-
-    let mut buf: [u16; MAX_PATH as usize] = [0; MAX_PATH as usize];
-
-    unsafe { K32GetModuleFileNameExW(h, None, &mut buf) };
-
-    let mut path_string = Utf16String::from_slice_lossy(&buf).to_string();
-    path_string.retain(|c| c != '\0');
-
-    let path = PathBuf::from(path_string);
+        let preferences = {
+            let container = handle.state::<ConfigContainer>();
+            let lock = container.0.lock().await;
 
-    return path.file_name().map(|s| s.to_string_lossy().into_owned());
-}
+            lock.get_preferences().clone()
+        };
 
-pub async fn overlay_poller(handle: AppHandle) {
-    let mut poller = Poller::default();
+        visibility.update();
 
-    loop {
-        let (overlay, overlay_hwnd) = {
-            let overlay = match handle.get_window("overlay") {
-                Some(h) => h,
-                None => return,
-            };
-
-            match overlay.raw_window_handle() {
-                RawWindowHandle::Win32(h) => {
-                    let hwnd = h.hwnd as isize;
-                    if hwnd == 0 {
-                        return;
-                    }
-
-                    (overlay, hwnd)
+        match tracker.poll(&windows, &preferences.game_targets) {
+            Some(PollResult::Open(_)) if !visibility.is_visible(&preferences.visibility) => {
+                hide_all(&windows)
+            }
+            Some(PollResult::Open(rect)) => {
+                let scale_factor = monitor_scale_factor(&rect, &windows[0]);
+
+                for (kind, window) in widgets.iter() {
+                    let layout = WidgetLayout::compute(
+                        kind.preferences(&preferences.widgets),
+                        kind.size(),
+                        &rect,
+                        scale_factor,
+                    );
+
+                    window.set_position(layout.position).unwrap();
+                    window.set_size(layout.size).unwrap();
+                    window.emit("show", layout.alignment).unwrap();
                 }
-                _ => return,
             }
-        };
+            Some(PollResult::Closed) => hide_all(&windows),
+            Some(PollResult::Retain) => (),
+            None => return,
+        }
 
-        match poller.poll(overlay_hwnd).await {
-            PollResult::Open(hwnd) => {
-                let mut dims = RECT::default();
+        let receiver = match changes.as_mut() {
+            Some(r) => r,
+            None => {
+                tokio::time::sleep(OVERLAY_POLL_INTERVAL).await;
+                continue;
+            }
+        };
 
-                unsafe { GetWindowRect(hwnd, &mut dims) };
+        // Nothing changes on its own besides preferences and visibility
+        // timeouts, so there's no need to poll while the tracker delivers
+        // notifications
+        let timeout = visibility.next_timeout(&preferences.visibility);
 
-                overlay
-                    .set_position(PhysicalPosition {
-                        x: dims.left,
-                        y: dims.top,
-                    })
-                    .unwrap();
+        let watching = tokio::select! {
+            received = receiver.recv() => received.is_some(),
+            _ = visibility.changed() => true,
+            _ = preferences_changed.notified() => true,
+            _ = sleep_until(timeout) => true,
+        };
 
-                overlay
-                    .set_size(PhysicalSize {
-                        width: dims.right - dims.left,
-                        height: dims.bottom - dims.top,
-                    })
-                    .unwrap();
+        // Coalesce bursts of notifications, e.g. while the window is dragged
+        while receiver.try_recv().is_ok() {}
 
-                overlay.emit("show", ()).unwrap();
-            }
-            PollResult::Closed => overlay.emit("hide", ()).unwrap(),
-            PollResult::Retain => (),
+        if !watching {
+            changes = None;
         }
+    }
+}
+
+fn hide_all(windows: &[Window]) {
+    for window in windows {
+        window.emit("hide", ()).unwrap();
+    }
+}
 
-        tokio::time::sleep(OVERLAY_POLL_INTERVAL).await;
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(d) => tokio::time::sleep_until(d.into()).await,
+        None => std::future::pending().await,
     }
 }
@@ -0,0 +1,221 @@
+use serde::Serialize;
+use tauri::{PhysicalPosition, PhysicalSize, Window};
+
+use crate::{
+    config::preferences::{OffsetUnit, OverlayAnchor, OverlayOffset, WidgetPreferences},
+    consts::OVERLAY_CORNER_MARGIN,
+    pollers::tracker::WindowRect,
+};
+
+pub struct WidgetLayout {
+    pub position: PhysicalPosition<i32>,
+    pub size: PhysicalSize<u32>,
+    pub alignment: WidgetAlignment,
+}
+
+// Tells the widget which corner of its window to draw from, and how much to
+// scale its contents by
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WidgetAlignment {
+    horizontal_edge: HorizontalEdge,
+    vertical_edge: VerticalEdge,
+    scale: f64,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum HorizontalEdge {
+    Left,
+    Right,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum VerticalEdge {
+    Top,
+    Bottom,
+}
+
+impl WidgetLayout {
+    // base_size is the widget's unscaled size in logical pixels
+    pub fn compute(
+        preferences: &WidgetPreferences,
+        base_size: (f64, f64),
+        rect: &WindowRect,
+        scale_factor: f64,
+    ) -> Self {
+        let (horizontal_edge, vertical_edge, margin) = match preferences.anchor {
+            OverlayAnchor::TopLeft => (
+                HorizontalEdge::Left,
+                VerticalEdge::Top,
+                OVERLAY_CORNER_MARGIN,
+            ),
+            OverlayAnchor::TopRight => (
+                HorizontalEdge::Right,
+                VerticalEdge::Top,
+                OVERLAY_CORNER_MARGIN,
+            ),
+            OverlayAnchor::BottomLeft => (
+                HorizontalEdge::Left,
+                VerticalEdge::Bottom,
+                OVERLAY_CORNER_MARGIN,
+            ),
+            OverlayAnchor::BottomRight => (
+                HorizontalEdge::Right,
+                VerticalEdge::Bottom,
+                OVERLAY_CORNER_MARGIN,
+            ),
+            OverlayAnchor::AboveMinimap | OverlayAnchor::Custom => {
+                (HorizontalEdge::Left, VerticalEdge::Top, 0.0)
+            }
+        };
+
+        let scale = preferences.scale.max(0.1);
+
+        let width = (base_size.0 * scale * scale_factor).round();
+        let height = (base_size.1 * scale * scale_factor).round();
+
+        let x = margin * scale_factor
+            + offset_to_physical(preferences.offset_x, rect.width, scale_factor);
+        let y = margin * scale_factor
+            + offset_to_physical(preferences.offset_y, rect.height, scale_factor);
+
+        let x = match horizontal_edge {
+            HorizontalEdge::Left => rect.x as f64 + x,
+            HorizontalEdge::Right => (rect.x + rect.width as i32) as f64 - x - width,
+        };
+
+        let y = match vertical_edge {
+            VerticalEdge::Top => rect.y as f64 + y,
+            VerticalEdge::Bottom => (rect.y + rect.height as i32) as f64 - y - height,
+        };
+
+        Self {
+            position: PhysicalPosition {
+                x: x.round() as i32,
+                y: y.round() as i32,
+            },
+            size: PhysicalSize {
+                width: width as u32,
+                height: height as u32,
+            },
+            alignment: WidgetAlignment {
+                horizontal_edge,
+                vertical_edge,
+                scale,
+            },
+        }
+    }
+}
+
+fn offset_to_physical(offset: OverlayOffset, extent: u32, scale_factor: f64) -> f64 {
+    match offset.unit {
+        OffsetUnit::Px => offset.value * scale_factor,
+        OffsetUnit::Percent => extent as f64 * offset.value / 100.0,
+    }
+}
+
+// Widgets are moved onto the game's monitor, so they're sized using that
+// monitor's scaling rather than the one they were on before
+pub fn monitor_scale_factor(rect: &WindowRect, window: &Window) -> f64 {
+    let center_x = rect.x + rect.width as i32 / 2;
+    let center_y = rect.y + rect.height as i32 / 2;
+
+    let monitor = window
+        .available_monitors()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|m| {
+            let position = m.position();
+            let size = m.size();
+
+            center_x >= position.x
+                && center_x < position.x + size.width as i32
+                && center_y >= position.y
+                && center_y < position.y + size.height as i32
+        });
+
+    match monitor {
+        Some(m) => m.scale_factor(),
+        None => window.scale_factor().unwrap_or(1.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECT: WindowRect = WindowRect {
+        x: 100,
+        y: 50,
+        width: 1920,
+        height: 1080,
+    };
+
+    fn widget(anchor: OverlayAnchor, offset_x: OverlayOffset, scale: f64) -> WidgetPreferences {
+        WidgetPreferences {
+            enabled: true,
+            anchor,
+            offset_x,
+            offset_y: OverlayOffset::default(),
+            scale,
+        }
+    }
+
+    fn px(value: f64) -> OverlayOffset {
+        OverlayOffset {
+            value,
+            unit: OffsetUnit::Px,
+        }
+    }
+
+    #[test]
+    fn top_left_is_offset_by_the_corner_margin() {
+        let layout = WidgetLayout::compute(
+            &widget(OverlayAnchor::TopLeft, px(10.0), 1.0),
+            (200.0, 40.0),
+            &RECT,
+            1.0,
+        );
+
+        assert_eq!((layout.position.x, layout.position.y), (134, 74));
+        assert_eq!((layout.size.width, layout.size.height), (200, 40));
+        assert!(layout.alignment.horizontal_edge == HorizontalEdge::Left);
+    }
+
+    #[test]
+    fn bottom_right_is_measured_from_the_far_edges() {
+        let layout = WidgetLayout::compute(
+            &widget(OverlayAnchor::BottomRight, px(10.0), 2.0),
+            (200.0, 40.0),
+            &RECT,
+            1.5,
+        );
+
+        // 600x120 physical, with a 36px margin and a 15px offset
+        assert_eq!((layout.size.width, layout.size.height), (600, 120));
+        assert_eq!((layout.position.x, layout.position.y), (1369, 974));
+        assert!(layout.alignment.vertical_edge == VerticalEdge::Bottom);
+        assert_eq!(layout.alignment.scale, 2.0);
+    }
+
+    #[test]
+    fn custom_offsets_can_be_relative_to_the_window() {
+        let offset = OverlayOffset {
+            value: 50.0,
+            unit: OffsetUnit::Percent,
+        };
+
+        let layout = WidgetLayout::compute(
+            &widget(OverlayAnchor::Custom, offset, 0.0),
+            (200.0, 40.0),
+            &RECT,
+            1.0,
+        );
+
+        // Scale is clamped so the widget never disappears
+        assert_eq!((layout.position.x, layout.position.y), (1060, 50));
+        assert_eq!((layout.size.width, layout.size.height), (20, 4));
+    }
+}
@@ -0,0 +1,247 @@
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Manager};
+use tokio::sync::{
+    broadcast::{
+        error::{RecvError, TryRecvError},
+        Receiver,
+    },
+    watch,
+};
+
+use crate::{
+    config::preferences::VisibilityPreferences,
+    consts::OVERLAY_SHOW_AFTER_CLEAR,
+    events::{EventBus, PlayerDataEvent},
+    pollers::playerdata::{PlayerDataStatus, PlayerState},
+};
+
+// Tracks what the player is doing so the overlay can be shown or hidden
+// according to the visibility preferences
+pub struct VisibilityTracker {
+    receiver: Receiver<PlayerDataEvent>,
+    // Watched to notice the poller being reset, e.g. on a profile switch
+    status: watch::Receiver<PlayerDataStatus>,
+    state: Option<PlayerState>,
+    last_change: Instant,
+    last_clear: Option<Instant>,
+}
+
+impl VisibilityTracker {
+    pub fn new(handle: &AppHandle) -> Self {
+        Self::from_bus(handle.state::<EventBus>().inner())
+    }
+
+    fn from_bus(bus: &EventBus) -> Self {
+        let receiver = bus.subscribe();
+        let mut status = bus.subscribe_status();
+
+        let state = status.borrow_and_update().current_state();
+
+        Self {
+            receiver,
+            status,
+            state,
+            last_change: Instant::now(),
+            last_clear: None,
+        }
+    }
+
+    pub fn update(&mut self) {
+        if self.status.has_changed().unwrap_or(false) {
+            self.sync_status();
+        }
+
+        loop {
+            match self.receiver.try_recv() {
+                Ok(e) => self.apply(e),
+                Err(TryRecvError::Lagged(_)) => (),
+                Err(TryRecvError::Empty | TryRecvError::Closed) => return,
+            }
+        }
+    }
+
+    // Waits for the next event, so the overlay can be updated as soon as
+    // the player's state changes
+    pub async fn changed(&mut self) {
+        tokio::select! {
+            event = self.receiver.recv() => match event {
+                Ok(e) => self.apply(e),
+                Err(RecvError::Lagged(_)) => (),
+                Err(RecvError::Closed) => std::future::pending().await,
+            },
+            res = self.status.changed() => match res {
+                Ok(_) => self.sync_status(),
+                Err(_) => std::future::pending().await,
+            },
+        }
+    }
+
+    // A reset poller starts over without any player data, and so does the
+    // tracker
+    fn sync_status(&mut self) {
+        if self.status.borrow_and_update().current_state().is_none() {
+            self.state = None;
+            self.last_change = Instant::now();
+            self.last_clear = None;
+        }
+    }
+
+    fn apply(&mut self, event: PlayerDataEvent) {
+        match event {
+            PlayerDataEvent::StateChanged(c) => {
+                self.state = Some(c.state);
+                self.last_change = Instant::now();
+            }
+            PlayerDataEvent::ActivityStarted(_) | PlayerDataEvent::ActivityChanged(_) => {
+                self.last_change = Instant::now();
+            }
+            PlayerDataEvent::ActivityCompleted(_) => {
+                self.last_clear = Some(Instant::now());
+                self.last_change = Instant::now();
+            }
+            _ => (),
+        }
+    }
+
+    // When the overlay could next be shown or hidden without any event, so
+    // the overlay poller knows when to wake up
+    pub fn next_timeout(&self, preferences: &VisibilityPreferences) -> Option<Instant> {
+        let clear_timeout = self
+            .last_clear
+            .filter(|_| preferences.show_after_clear)
+            .map(|c| c + OVERLAY_SHOW_AFTER_CLEAR);
+
+        let inactive_timeout = match self.state {
+            Some(PlayerState::Offline | PlayerState::Orbit)
+                if preferences.hide_after_inactive_secs > 0 =>
+            {
+                Some(self.last_change + Duration::from_secs(preferences.hide_after_inactive_secs))
+            }
+            _ => None,
+        };
+
+        let now = Instant::now();
+
+        [clear_timeout, inactive_timeout]
+            .into_iter()
+            .flatten()
+            .filter(|t| *t > now)
+            .min()
+    }
+
+    pub fn is_visible(&self, preferences: &VisibilityPreferences) -> bool {
+        if preferences.show_after_clear {
+            if let Some(cleared) = self.last_clear {
+                if cleared.elapsed() < OVERLAY_SHOW_AFTER_CLEAR {
+                    return true;
+                }
+            }
+        }
+
+        // Nothing is hidden until the first poll tells us what the player is doing
+        let state = match self.state {
+            Some(s) => s,
+            None => return true,
+        };
+
+        if preferences.only_in_tracked_activities && state != PlayerState::InTrackedActivity {
+            return false;
+        }
+
+        if preferences.hide_in_orbit && state == PlayerState::Orbit {
+            return false;
+        }
+
+        // Long activities can go without any events, so inactivity only
+        // counts outside of them
+        let in_activity = matches!(
+            state,
+            PlayerState::InTrackedActivity | PlayerState::InUntrackedActivity
+        );
+
+        if preferences.hide_after_inactive_secs > 0
+            && !in_activity
+            && self.last_change.elapsed()
+                > Duration::from_secs(preferences.hide_after_inactive_secs)
+        {
+            return false;
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preferences(hide_after_inactive_secs: u64) -> VisibilityPreferences {
+        VisibilityPreferences {
+            only_in_tracked_activities: false,
+            hide_in_orbit: false,
+            hide_after_inactive_secs,
+            show_after_clear: false,
+        }
+    }
+
+    fn idle_tracker(bus: &EventBus, state: PlayerState) -> VisibilityTracker {
+        let mut tracker = VisibilityTracker::from_bus(bus);
+        tracker.state = Some(state);
+        tracker.last_change = Instant::now() - Duration::from_secs(120);
+
+        tracker
+    }
+
+    #[test]
+    fn hides_when_inactive_outside_activities() {
+        let bus = EventBus::default();
+
+        assert!(!idle_tracker(&bus, PlayerState::Orbit).is_visible(&preferences(60)));
+        assert!(idle_tracker(&bus, PlayerState::Orbit).is_visible(&preferences(0)));
+    }
+
+    #[test]
+    fn stays_visible_during_long_activities() {
+        let bus = EventBus::default();
+
+        for state in [
+            PlayerState::InTrackedActivity,
+            PlayerState::InUntrackedActivity,
+        ] {
+            assert!(idle_tracker(&bus, state).is_visible(&preferences(60)));
+        }
+    }
+
+    #[test]
+    fn wakes_up_when_inactivity_would_hide() {
+        let bus = EventBus::default();
+        let mut tracker = VisibilityTracker::from_bus(&bus);
+        tracker.state = Some(PlayerState::Orbit);
+
+        let timeout = tracker.next_timeout(&preferences(60)).unwrap();
+        assert_eq!(timeout, tracker.last_change + Duration::from_secs(60));
+
+        tracker.state = Some(PlayerState::InTrackedActivity);
+        assert!(tracker.next_timeout(&preferences(60)).is_none());
+
+        // Already hidden, so there's nothing left to wait for
+        assert!(idle_tracker(&bus, PlayerState::Orbit)
+            .next_timeout(&preferences(60))
+            .is_none());
+    }
+
+    #[test]
+    fn resets_with_the_poller() {
+        let bus = EventBus::default();
+        let mut tracker = idle_tracker(&bus, PlayerState::Orbit);
+        tracker.last_clear = Some(Instant::now());
+
+        bus.send_status(PlayerDataStatus::default());
+        tracker.update();
+
+        assert!(tracker.state.is_none());
+        assert!(tracker.last_clear.is_none());
+        assert!(tracker.is_visible(&preferences(60)));
+    }
+}
@@ -1,23 +1,24 @@
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc};
 
 use anyhow::{anyhow, bail, Result};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Utc, Weekday};
 use serde::Serialize;
-use tauri::{
-    async_runtime::{self, JoinHandle},
-    AppHandle, Manager,
-};
-use tokio::sync::Mutex;
+use tauri::async_runtime::{self, JoinHandle};
+use tokio::sync::{watch, Mutex};
 
+use super::{
+    host::PollerHost,
+    schedule::{PollKind, PollScheduler},
+};
 use crate::{
     api::{
         requests::BungieResponseError,
-        responses::{ActivityInfo, CompletedActivity, LatestCharacterActivity, ProfileInfo},
+        responses::{ActivityInfo, CharacterInfo, CompletedActivity, FireteamMember, ProfileInfo},
         Api, ApiError, Source,
     },
     config::profiles::Profile,
-    consts::{DUNGEON_ACTIVITY_MODE, RAID_ACTIVITY_MODE, STRIKE_ACTIVITY_MODE, LOSTSECTOR_ACTIVITY_MODE},
-    ConfigContainer,
+    consts::{ORBIT_ACTIVITY_HASH, PROCESS_POLL_INTERVAL, TRACKED_ACTIVITY_MODES},
+    events::{ClearMilestone, PersonalBest, PlayerDataEvent, StateChange},
 };
 
 #[derive(Serialize, Clone)]
@@ -26,6 +27,7 @@ pub struct PlayerData {
     current_activity: CurrentActivity,
     activity_history: Vec<CompletedActivity>,
     profile_info: ProfileInfo,
+    sessions: Vec<PlaySession>,
 }
 
 #[derive(Serialize, Default, Clone)]
@@ -35,22 +37,54 @@ pub struct PlayerDataStatus {
     error: Option<String>,
 }
 
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PlayerState {
+    Offline,
+    Orbit,
+    InTrackedActivity,
+    InUntrackedActivity,
+}
+
 #[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
-struct CurrentActivity {
+pub struct CurrentActivity {
+    state: PlayerState,
     start_date: DateTime<Utc>,
     activity_hash: usize,
     activity_info: Option<ActivityInfo>,
+    character_id: Option<String>,
+    character: Option<CharacterInfo>,
+    fireteam: Vec<FireteamMember>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PlaySession {
+    start: DateTime<Utc>,
+    end: Option<DateTime<Utc>>,
+    activities: Vec<SessionActivity>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SessionActivity {
+    start_date: DateTime<Utc>,
+    activity_hash: usize,
 }
 
 #[derive(Default)]
 pub struct PlayerDataPoller {
     task_handle: Option<JoinHandle<()>>,
     current_playerdata: Arc<Mutex<PlayerDataStatus>>,
+    // Kept per profile so sessions survive the poller being reset
+    sessions: Arc<Mutex<HashMap<Profile, Vec<PlaySession>>>>,
+    // Likewise, so they're only fetched once per profile
+    personal_bests: Arc<Mutex<HashMap<Profile, PersonalBests>>>,
 }
 
 impl PlayerDataPoller {
-    pub async fn reset(&mut self, app_handle: AppHandle) {
+    pub async fn reset<H: PollerHost>(&mut self, host: H) {
         if let Some(t) = self.task_handle.as_ref() {
             t.abort();
         }
@@ -59,15 +93,16 @@ impl PlayerDataPoller {
             let mut lock = self.current_playerdata.lock().await;
             *lock = PlayerDataStatus::default();
 
-            send_data_update(&app_handle, lock.clone());
+            send_data_update(&host, &lock);
         }
 
         let playerdata_clone = self.current_playerdata.clone();
+        let sessions_clone = self.sessions.clone();
+        let personal_bests_clone = self.personal_bests.clone();
 
         self.task_handle = Some(async_runtime::spawn(async move {
             let profile = {
-                let container = app_handle.state::<ConfigContainer>();
-                let lock = container.0.lock().await;
+                let lock = host.config().0.lock().await;
 
                 match &lock.get_profiles().selected_profile {
                     Some(p) => p.clone(),
@@ -75,15 +110,14 @@ impl PlayerDataPoller {
                         let mut lock = playerdata_clone.lock().await;
                         lock.error = Some("No profile set".to_string());
 
-                        send_data_update(&app_handle, lock.clone());
+                        send_data_update(&host, &lock);
                         return;
                     }
                 }
             };
 
             let profile_info = {
-                let api = app_handle.state::<Api>();
-                let mut lock = api.profile_info_source.lock().await;
+                let mut lock = host.api().profile_info_source.lock().await;
 
                 match lock.get(&profile).await {
                     Ok(p) => p,
@@ -91,57 +125,138 @@ impl PlayerDataPoller {
                         let mut lock = playerdata_clone.lock().await;
                         lock.error = Some(format!("Failed to get profile info: {e}"));
 
-                        send_data_update(&app_handle, lock.clone());
+                        send_data_update(&host, &lock);
                         return;
                     }
                 }
             };
 
             let mut current_activity = CurrentActivity {
+                state: PlayerState::Offline,
                 start_date: DateTime::<Utc>::MIN_UTC,
                 activity_hash: 0,
                 activity_info: None,
+                character_id: None,
+                character: None,
+                fireteam: Vec::new(),
             };
             let mut activity_history = Vec::new();
 
-            let res = match update_current(&app_handle, &mut current_activity, &profile).await {
-                Ok(_) => update_history(&app_handle, &mut activity_history, &profile).await,
+            let res = match update_current(&host, &mut current_activity, &profile).await {
+                Ok(_) => update_history(&host, &mut activity_history, &profile).await,
                 Err(e) => Err(e),
             };
 
-            {
+            let current_state = current_activity.state;
+
+            if let Err(e) = res {
                 let mut lock = playerdata_clone.lock().await;
-                match res {
-                    Ok(_) => {
-                        let playerdata = PlayerData {
-                            current_activity: current_activity,
-                            activity_history,
-                            profile_info,
-                        };
-
-                        lock.last_update = Some(playerdata);
-                        send_data_update(&app_handle, lock.clone());
-                    }
-                    Err(e) => {
-                        lock.error = Some(e.to_string());
-                        send_data_update(&app_handle, lock.clone());
-                        return;
-                    }
+                lock.error = Some(e.to_string());
+
+                send_data_update(&host, &lock);
+                return;
+            }
+
+            {
+                let mut lock = personal_bests_clone.lock().await;
+
+                // Fetched again after a failure, as first clears can't be
+                // told apart without them
+                if !lock.get(&profile).map_or(false, |b| b.seeded) {
+                    let personal_bests = PersonalBests::fetch(&profile, &profile_info).await;
+                    lock.insert(profile.clone(), personal_bests);
                 }
+
+                let personal_bests = lock.get_mut(&profile).unwrap();
+
+                for activity in activity_history.iter() {
+                    personal_bests.record(activity);
+                }
+            }
+
+            {
+                let mut lock = playerdata_clone.lock().await;
+
+                let mut sessions_lock = sessions_clone.lock().await;
+                let sessions = sessions_lock.entry(profile.clone()).or_default();
+
+                update_sessions(sessions, &current_activity, Utc::now(), true);
+                let sessions = sessions.clone();
+
+                let playerdata = PlayerData {
+                    current_activity: current_activity,
+                    activity_history,
+                    profile_info,
+                    sessions,
+                };
+
+                lock.last_update = Some(playerdata);
+                send_data_update(&host, &lock);
             }
 
-            let mut count = 0;
+            let mut scheduler = PollScheduler::default();
+            scheduler.record_state(current_state, true);
+
+            let mut game_running = host.game_running();
 
             loop {
-                tokio::time::sleep(Duration::from_secs(2)).await;
+                let (preferences, clear_milestone) = {
+                    let lock = host.config().0.lock().await;
+                    let preferences = lock.get_preferences();
+
+                    (
+                        preferences.polling.clone(),
+                        preferences.daily_clear_milestone,
+                    )
+                };
+
+                if preferences.suspend_when_game_closed {
+                    wait_for_game(&host, &mut game_running).await;
+                }
+
+                let (interval, kind) = scheduler.next(&preferences, *game_running.borrow());
+
+                tokio::select! {
+                    _ = tokio::time::sleep(interval) => (),
+                    // Poll straight away when the game is launched or closed
+                    Ok(_) = game_running.changed() => (),
+                }
 
                 let mut last_update = playerdata_clone.lock().await.last_update.clone().unwrap();
 
-                let res = if count < 5 {
-                    update_current(&app_handle, &mut last_update.current_activity, &profile).await
-                } else {
-                    count = 0;
-                    update_history(&app_handle, &mut last_update.activity_history, &profile).await
+                let res = match kind {
+                    PollKind::Current => {
+                        let previous_start = last_update.current_activity.start_date;
+
+                        let res =
+                            update_current(&host, &mut last_update.current_activity, &profile)
+                                .await;
+
+                        if let Ok(true) = res {
+                            let current = &last_update.current_activity;
+
+                            update_sessions(&mut last_update.sessions, current, Utc::now(), false);
+                            sessions_clone
+                                .lock()
+                                .await
+                                .insert(profile.clone(), last_update.sessions.clone());
+                            scheduler
+                                .record_state(current.state, current.start_date != previous_start);
+                        }
+
+                        res
+                    }
+                    PollKind::History => {
+                        let res =
+                            update_history(&host, &mut last_update.activity_history, &profile)
+                                .await;
+
+                        if let Ok(changed) = res {
+                            scheduler.record_history(changed);
+                        }
+
+                        res
+                    }
                 };
 
                 // The boolean return value of update_* functions represents whether or not
@@ -150,21 +265,41 @@ impl PlayerDataPoller {
                 match res {
                     Ok(true) => {
                         let mut lock = playerdata_clone.lock().await;
+
+                        if let Some(previous) = lock.last_update.as_ref() {
+                            let bus = host.event_bus();
+
+                            let mut personal_bests = personal_bests_clone.lock().await;
+                            let personal_bests = personal_bests.entry(profile.clone()).or_default();
+
+                            state_event(&previous.current_activity, &last_update.current_activity)
+                                .into_iter()
+                                .chain(activity_event(
+                                    &previous.current_activity,
+                                    &last_update.current_activity,
+                                ))
+                                .chain(history_events(
+                                    &previous.activity_history,
+                                    &last_update.activity_history,
+                                    personal_bests,
+                                    clear_milestone,
+                                ))
+                                .for_each(|e| bus.send(e));
+                        }
+
                         lock.error = None;
                         lock.last_update = Some(last_update);
 
-                        send_data_update(&app_handle, lock.clone())
+                        send_data_update(&host, &lock)
                     }
                     Err(e) => {
                         let mut lock = playerdata_clone.lock().await;
                         lock.error = Some(e.to_string());
 
-                        send_data_update(&app_handle, lock.clone())
+                        send_data_update(&host, &lock)
                     }
                     _ => (),
                 }
-
-                count += 1;
             }
         }));
     }
@@ -178,18 +313,170 @@ impl PlayerDataPoller {
     }
 }
 
-fn send_data_update(handle: &AppHandle, data: PlayerDataStatus) {
-    if let Some(o) = handle.get_window("overlay") {
-        o.emit("playerdata_update", data.clone()).unwrap();
+impl PlayerDataStatus {
+    pub fn current_state(&self) -> Option<PlayerState> {
+        self.last_update.as_ref().map(|p| p.current_activity.state)
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+// The preference is checked again every so often, so turning it off while
+// suspended resumes polling without waiting for the game
+async fn wait_for_game<H: PollerHost>(host: &H, game_running: &mut watch::Receiver<bool>) {
+    while !*game_running.borrow_and_update() {
+        tokio::select! {
+            res = game_running.changed() => {
+                if res.is_err() {
+                    return;
+                }
+            }
+            _ = tokio::time::sleep(PROCESS_POLL_INTERVAL) => {
+                let lock = host.config().0.lock().await;
+
+                if !lock.get_preferences().polling.suspend_when_game_closed {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn send_data_update<H: PollerHost>(host: &H, data: &PlayerDataStatus) {
+    host.event_bus().send_status(data.clone());
+    host.send_data_update(data);
+}
+
+fn state_event(previous: &CurrentActivity, current: &CurrentActivity) -> Option<PlayerDataEvent> {
+    if previous.state == current.state {
+        return None;
+    }
+
+    Some(PlayerDataEvent::StateChanged(StateChange {
+        previous: previous.state,
+        state: current.state,
+    }))
+}
+
+fn activity_event(
+    previous: &CurrentActivity,
+    current: &CurrentActivity,
+) -> Option<PlayerDataEvent> {
+    match current.state {
+        PlayerState::InTrackedActivity | PlayerState::InUntrackedActivity => (),
+        _ => return None,
+    }
+
+    if previous.start_date != current.start_date {
+        Some(PlayerDataEvent::ActivityStarted(current.clone()))
+    } else if previous.activity_hash != current.activity_hash {
+        Some(PlayerDataEvent::ActivityChanged(current.clone()))
+    } else {
+        None
     }
+}
+
+fn history_events(
+    previous: &[CompletedActivity],
+    current: &[CompletedActivity],
+    personal_bests: &mut PersonalBests,
+    clear_milestone: usize,
+) -> Vec<PlayerDataEvent> {
+    let mut events = Vec::new();
+
+    // History is sorted newest first, so walk it backwards to keep events in order
+    for activity in current
+        .iter()
+        .rev()
+        .filter(|a| !previous.iter().any(|p| p.instance_id == a.instance_id))
+    {
+        if !activity.completed {
+            events.push(PlayerDataEvent::ActivityFailed(activity.clone()));
+            continue;
+        }
+
+        events.push(PlayerDataEvent::ActivityCompleted(activity.clone()));
+
+        if let Some(best) = personal_bests.record(activity) {
+            events.push(PlayerDataEvent::NewPersonalBest(best));
+        }
+    }
+
+    let previous_clears = previous.iter().filter(|a| a.completed).count();
+    let clears = current.iter().filter(|a| a.completed).count();
+
+    // A milestone of 0 turns the event off
+    if clear_milestone > 0 && clears / clear_milestone > previous_clears / clear_milestone {
+        events.push(PlayerDataEvent::DailyClearMilestone(ClearMilestone {
+            clears,
+        }));
+    }
+
+    events
+}
+
+// Fastest clear of each activity hash, in seconds
+#[derive(Default)]
+struct PersonalBests {
+    seconds: HashMap<usize, usize>,
+    // Without the profile's aggregate stats, an activity missing from the
+    // table may well have been cleared before
+    seeded: bool,
+}
+
+impl PersonalBests {
+    async fn fetch(profile: &Profile, profile_info: &ProfileInfo) -> Self {
+        let mut bests = Self {
+            seeded: true,
+            ..Default::default()
+        };
+
+        for character_id in profile_info.character_ids.iter() {
+            match Api::get_aggregate_activity_stats(profile, character_id).await {
+                Ok(stats) => {
+                    for (hash, seconds) in stats.fastest_seconds {
+                        let best = bests.seconds.entry(hash).or_insert(seconds);
+                        *best = (*best).min(seconds);
+                    }
+                }
+                Err(_) => bests.seeded = false,
+            }
+        }
+
+        bests
+    }
+
+    // Returns a personal best if the given clear beats the previous one, or
+    // is the first clear of the activity
+    fn record(&mut self, activity: &CompletedActivity) -> Option<PersonalBest> {
+        if !activity.completed {
+            return None;
+        }
+
+        let duration = activity.activity_duration_seconds;
+
+        let previous_best_seconds = match self.seconds.get(&activity.activity_hash).copied() {
+            Some(best) if best <= duration => return None,
+            None if !self.seeded => {
+                self.seconds.insert(activity.activity_hash, duration);
+                return None;
+            }
+            previous_best => previous_best,
+        };
 
-    if let Some(o) = handle.get_window("details") {
-        o.emit("playerdata_update", data).unwrap();
+        self.seconds.insert(activity.activity_hash, duration);
+
+        Some(PersonalBest {
+            activity: activity.clone(),
+            previous_best_seconds,
+        })
     }
 }
 
-async fn update_current(
-    handle: &AppHandle,
+async fn update_current<H: PollerHost>(
+    host: &H,
     last_activity: &mut CurrentActivity,
     profile: &Profile,
 ) -> Result<bool> {
@@ -200,51 +487,105 @@ async fn update_current(
         None => bail!("Profile is private"),
     };
 
-    let (characters, activities): (Vec<String>, Vec<LatestCharacterActivity>) =
-        activities.into_iter().unzip();
+    let characters: Vec<String> = activities.keys().cloned().collect();
 
-    let latest_activity = activities
+    let (character_id, latest_activity) = activities
         .into_iter()
-        .max()
+        .max_by(|a, b| a.1.cmp(&b.1))
         .ok_or(anyhow!("No character data for profile"))?;
 
+    let character_switched = last_activity.character_id.as_ref() != Some(&character_id);
+
+    if character_switched {
+        if last_activity.character_id.is_some() {
+            host.send_character_switch(&character_id);
+        }
+
+        last_activity.character_id = Some(character_id.clone());
+    }
+
+    let fireteam_changed = last_activity.fireteam != current_activities.fireteam;
+    last_activity.fireteam = current_activities.fireteam;
+
+    // Stored even when the activity itself is unchanged, so the switch is
+    // only reported once
+    let changed = fireteam_changed || character_switched;
+
+    if !current_activities.online {
+        if last_activity.state == PlayerState::Offline {
+            return Ok(changed);
+        }
+
+        last_activity.state = PlayerState::Offline;
+        last_activity.activity_info = None;
+        return Ok(true);
+    }
+
+    let was_offline = last_activity.state == PlayerState::Offline;
+    let mut activity_started = false;
+
     match last_activity
         .start_date
         .cmp(&latest_activity.date_activity_started)
     {
         std::cmp::Ordering::Less => {
-            last_activity.start_date = latest_activity.date_activity_started
+            last_activity.start_date = latest_activity.date_activity_started;
+            activity_started = true;
         }
-        std::cmp::Ordering::Equal => {
+        std::cmp::Ordering::Equal if !was_offline => {
             if last_activity.activity_info.is_none() {
-                return Ok(false);
+                return Ok(changed);
                 // Return here, as once activity_info becomes None
                 // for a given activity start_date, it should
                 // stay None until start_date changes again
             }
 
             if last_activity.activity_hash == latest_activity.current_activity_hash {
-                return Ok(false);
+                return Ok(changed);
                 // Return if the activity hash and time are the same
             }
         }
-        std::cmp::Ordering::Greater => return Ok(false),
+        std::cmp::Ordering::Equal => (),
+        // Coming back online can leave start_date unchanged, but the
+        // activity still needs to be resolved again
+        std::cmp::Ordering::Greater => return Ok(changed),
         // Only return if our last-fetched activity is more recent,
         // as current_hash can change without start_date changing
     }
 
-    let api = handle.state::<Api>();
+    let api = host.api();
 
     api.profile_info_source
         .lock()
         .await
         .set_characters(profile, characters);
 
-    if latest_activity.current_activity_hash == 0 {
+    // Light level and emblem only change between activities. They're only
+    // shown alongside the activity, which is tracked even if they can't load
+    last_activity.character = {
+        let mut lock = api.character_info_source.lock().await;
+
+        if character_switched || activity_started {
+            lock.invalidate(profile);
+        }
+
+        lock.get(profile)
+            .await
+            .ok()
+            .and_then(|c| c.characters)
+            .and_then(|mut c| c.remove(&character_id))
+    };
+
+    if latest_activity.current_activity_hash == 0
+        || latest_activity.current_activity_hash == ORBIT_ACTIVITY_HASH
+    {
         last_activity.activity_info = None;
+        last_activity.state = PlayerState::Orbit;
         return Ok(true);
     }
 
+    last_activity.state = PlayerState::InUntrackedActivity;
+
     let current_activity_info = {
         let activity = api
             .activity_info_source
@@ -268,71 +609,100 @@ async fn update_current(
         return Ok(true);
     }
 
+    if current_activity_info
+        .activity_modes
+        .iter()
+        .any(|m| TRACKED_ACTIVITY_MODES.contains(m))
+    {
+        last_activity.state = PlayerState::InTrackedActivity;
+    }
+
     last_activity.activity_hash = latest_activity.current_activity_hash;
     last_activity.activity_info = Some(current_activity_info);
 
     Ok(true)
 }
 
-async fn update_history(
-    handle: &AppHandle,
+// A session starts when the player is first seen online. When the poller
+// starts with the player already online, the current activity's start is
+// the earliest time they're known to have been online
+fn update_sessions(
+    sessions: &mut Vec<PlaySession>,
+    current_activity: &CurrentActivity,
+    now: DateTime<Utc>,
+    first_poll: bool,
+) {
+    let open_session = sessions.last_mut().filter(|s| s.end.is_none());
+
+    let session = match (current_activity.state, open_session) {
+        (PlayerState::Offline, Some(s)) => {
+            s.end = Some(now);
+            return;
+        }
+        (PlayerState::Offline, None) => return,
+        (_, Some(s)) => s,
+        (_, None) => {
+            sessions.push(PlaySession {
+                start: if first_poll {
+                    current_activity.start_date
+                } else {
+                    now
+                },
+                end: None,
+                activities: Vec::new(),
+            });
+
+            sessions.last_mut().unwrap()
+        }
+    };
+
+    if current_activity.state == PlayerState::Orbit {
+        return;
+    }
+
+    if session
+        .activities
+        .last()
+        .map_or(true, |a| a.start_date != current_activity.start_date)
+    {
+        session.activities.push(SessionActivity {
+            start_date: current_activity.start_date,
+            activity_hash: current_activity.activity_hash,
+        });
+    }
+}
+
+async fn update_history<H: PollerHost>(
+    host: &H,
     last_history: &mut Vec<CompletedActivity>,
     profile: &Profile,
 ) -> Result<bool> {
-    let api = handle.state::<Api>();
+    let api = host.api();
 
     let profile_info = api.profile_info_source.lock().await.get(profile).await?;
 
-    let mut past_activities: Vec<CompletedActivity> = Vec::new();
-
-    let cutoff = {
-        let now = Utc::now();
-        let naive_cutoff = now
-            .date_naive()
-            .and_hms_opt(17, 0, 0)
-            .ok_or(anyhow!("There is no 5PM UTC today?"))?;
+    let cutoff = daily_reset()?;
 
-        let mut time = DateTime::<Utc>::from_utc(naive_cutoff, Utc);
-
-        if time > now {
-            time -= chrono::Duration::days(1);
-        }
+    let mut past_activities: Vec<CompletedActivity> = Vec::new();
 
-        time
-    };
+    let characters = api
+        .character_info_source
+        .lock()
+        .await
+        .get(profile)
+        .await?
+        .characters
+        .unwrap_or_default();
 
     for character_id in profile_info.character_ids.iter() {
-        let mut page = 0;
-
-        loop {
-            let history = Api::get_activity_history(profile, character_id, page).await?;
-
-            let activities = match history.activities {
-                Some(a) => a,
-                None => break,
-            };
-
-            let mut includes_past_cutoff = false;
-
-            for activity in activities.into_iter() {
-                if activity.period < cutoff {
-                    includes_past_cutoff = true;
-                } else if activity.modes.iter().any(|m| {
-                    *m == RAID_ACTIVITY_MODE
-                        || *m == DUNGEON_ACTIVITY_MODE
-                        || *m == STRIKE_ACTIVITY_MODE
-                        || *m == LOSTSECTOR_ACTIVITY_MODE
-                }) {
-                    past_activities.push(activity);
-                }
-            }
+        let mut activities =
+            fetch_history_since(profile, character_id, cutoff, TRACKED_ACTIVITY_MODES).await?;
 
-            if includes_past_cutoff {
-                break;
-            }
-
-            page += 1;
+        for activity in activities.iter_mut() {
+            activity.character = characters.get(character_id).cloned();
         }
+
+        past_activities.append(&mut activities);
     }
 
     if let Some(last) = last_history.into_iter().max() {
@@ -351,3 +721,204 @@ async fn update_history(
 
     Ok(true)
 }
+
+pub async fn fetch_history_since(
+    profile: &Profile,
+    character_id: &String,
+    cutoff: DateTime<Utc>,
+    modes: &[usize],
+) -> Result<Vec<CompletedActivity>> {
+    let mut past_activities = Vec::new();
+    let mut page = 0;
+
+    loop {
+        let history = Api::get_activity_history(profile, character_id, page).await?;
+
+        let activities = match history.activities {
+            Some(a) => a,
+            None => break,
+        };
+
+        let mut includes_past_cutoff = false;
+
+        for activity in activities.into_iter() {
+            if activity.period < cutoff {
+                includes_past_cutoff = true;
+            } else if activity.modes.iter().any(|m| modes.contains(m)) {
+                past_activities.push(activity);
+            }
+        }
+
+        if includes_past_cutoff {
+            break;
+        }
+
+        page += 1;
+    }
+
+    Ok(past_activities)
+}
+
+pub fn daily_reset() -> Result<DateTime<Utc>> {
+    daily_reset_before(Utc::now())
+}
+
+pub fn weekly_reset() -> Result<DateTime<Utc>> {
+    weekly_reset_before(Utc::now())
+}
+
+fn daily_reset_before(now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let naive_cutoff = now
+        .date_naive()
+        .and_hms_opt(17, 0, 0)
+        .ok_or(anyhow!("There is no 5PM UTC today?"))?;
+
+    let mut time = DateTime::<Utc>::from_utc(naive_cutoff, Utc);
+
+    if time > now {
+        time -= chrono::Duration::days(1);
+    }
+
+    Ok(time)
+}
+
+fn weekly_reset_before(now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let daily = daily_reset_before(now)?;
+
+    // Weekly reset happens at the same time as daily reset, but only on Tuesdays
+    let days_since =
+        (daily.weekday().num_days_from_monday() + 7 - Weekday::Tue.num_days_from_monday()) % 7;
+
+    Ok(daily - chrono::Duration::days(days_since as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn utc(y: i32, m: u32, d: u32, h: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap()
+    }
+
+    fn activity(state: PlayerState, start_date: DateTime<Utc>) -> CurrentActivity {
+        CurrentActivity {
+            state,
+            start_date,
+            activity_hash: 1,
+            activity_info: None,
+            character_id: None,
+            character: None,
+            fireteam: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn sessions_start_when_the_player_comes_online() {
+        let mut sessions = Vec::new();
+
+        let orbit = activity(PlayerState::Orbit, utc(2023, 3, 15, 10));
+        update_sessions(&mut sessions, &orbit, utc(2023, 3, 15, 12), true);
+        assert_eq!(sessions[0].start, utc(2023, 3, 15, 10));
+
+        let offline = activity(PlayerState::Offline, utc(2023, 3, 15, 10));
+        update_sessions(&mut sessions, &offline, utc(2023, 3, 15, 13), false);
+        assert_eq!(sessions[0].end, Some(utc(2023, 3, 15, 13)));
+
+        // Coming back online reports an activity that started before the
+        // player was last seen offline
+        let orbit = activity(PlayerState::Orbit, utc(2023, 3, 15, 11));
+        update_sessions(&mut sessions, &orbit, utc(2023, 3, 15, 14), false);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[1].start, utc(2023, 3, 15, 14));
+        assert!(sessions[1].activities.is_empty());
+
+        let raid = activity(PlayerState::InTrackedActivity, utc(2023, 3, 15, 15));
+        update_sessions(&mut sessions, &raid, utc(2023, 3, 15, 15), false);
+        update_sessions(&mut sessions, &raid, utc(2023, 3, 15, 16), false);
+        assert_eq!(sessions[1].activities.len(), 1);
+    }
+
+    fn clear(activity_hash: usize, seconds: usize, completed: bool) -> CompletedActivity {
+        CompletedActivity {
+            period: utc(2023, 3, 15, 18),
+            instance_id: format!("{activity_hash}-{seconds}"),
+            activity_hash,
+            modes: vec![4],
+            completed,
+            activity_duration: String::new(),
+            activity_duration_seconds: seconds,
+            character: None,
+        }
+    }
+
+    #[test]
+    fn personal_bests_only_count_faster_clears() {
+        let mut bests = PersonalBests {
+            seconds: HashMap::from([(1, 600)]),
+            seeded: true,
+        };
+
+        assert!(bests.record(&clear(1, 700, true)).is_none());
+        assert!(bests.record(&clear(1, 500, false)).is_none());
+
+        let best = bests.record(&clear(1, 500, true)).unwrap();
+        assert_eq!(best.previous_best_seconds, Some(600));
+
+        let first = bests.record(&clear(2, 900, true)).unwrap();
+        assert_eq!(first.previous_best_seconds, None);
+        assert!(bests.record(&clear(2, 950, true)).is_none());
+    }
+
+    #[test]
+    fn unseeded_personal_bests_skip_first_clears() {
+        let mut bests = PersonalBests::default();
+
+        assert!(bests.record(&clear(1, 600, true)).is_none());
+        assert_eq!(
+            bests
+                .record(&clear(1, 500, true))
+                .unwrap()
+                .previous_best_seconds,
+            Some(600)
+        );
+    }
+
+    #[test]
+    fn daily_reset_is_the_last_5pm_utc() {
+        // 2023-03-15 is a Wednesday
+        assert_eq!(
+            daily_reset_before(utc(2023, 3, 15, 18)).unwrap(),
+            utc(2023, 3, 15, 17)
+        );
+        assert_eq!(
+            daily_reset_before(utc(2023, 3, 15, 17)).unwrap(),
+            utc(2023, 3, 15, 17)
+        );
+        assert_eq!(
+            daily_reset_before(utc(2023, 3, 15, 16)).unwrap(),
+            utc(2023, 3, 14, 17)
+        );
+        assert_eq!(
+            daily_reset_before(utc(2023, 3, 1, 3)).unwrap(),
+            utc(2023, 2, 28, 17)
+        );
+    }
+
+    #[test]
+    fn weekly_reset_is_the_last_tuesday_reset() {
+        assert_eq!(
+            weekly_reset_before(utc(2023, 3, 14, 18)).unwrap(),
+            utc(2023, 3, 14, 17)
+        );
+        assert_eq!(
+            weekly_reset_before(utc(2023, 3, 14, 16)).unwrap(),
+            utc(2023, 3, 7, 17)
+        );
+        assert_eq!(
+            weekly_reset_before(utc(2023, 3, 20, 12)).unwrap(),
+            utc(2023, 3, 14, 17)
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use tauri::{async_runtime, AppHandle};
+use tokio::sync::watch::{self, Receiver, Sender};
+
+#[cfg(target_os = "linux")]
+pub use self::procfs::process_exec_names;
+#[cfg(target_os = "windows")]
+pub use self::win32::process_exec_name;
+use super::host::PollerHost;
+use crate::{config::preferences::GameTargets, consts::PROCESS_POLL_INTERVAL};
+
+#[cfg(target_os = "linux")]
+mod procfs;
+#[cfg(target_os = "windows")]
+mod win32;
+
+pub struct GameProcessState(pub Receiver<bool>);
+
+pub fn spawn_process_watcher(handle: AppHandle) -> GameProcessState {
+    let (sender, receiver) = watch::channel(false);
+
+    async_runtime::spawn(watch_game_process(handle, sender));
+
+    GameProcessState(receiver)
+}
+
+pub async fn watch_game_process<H: PollerHost>(host: H, sender: Sender<bool>) {
+    loop {
+        let targets = {
+            let lock = host.config().0.lock().await;
+
+            lock.get_preferences().game_targets.clone()
+        };
+
+        let running = is_game_running(&targets);
+
+        if *sender.borrow() != running && sender.send(running).is_err() {
+            return;
+        }
+
+        tokio::time::sleep(PROCESS_POLL_INTERVAL).await;
+    }
+}
+
+fn is_game_running(targets: &GameTargets) -> bool {
+    process_names().iter().any(|n| targets.matches(n))
+}
+
+#[cfg(target_os = "windows")]
+fn process_names() -> Vec<String> {
+    win32::process_names()
+}
+
+#[cfg(target_os = "linux")]
+fn process_names() -> Vec<String> {
+    procfs::process_names()
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn process_names() -> Vec<String> {
+    Vec::new()
+}
@@ -0,0 +1,46 @@
+use std::{fs, path::Path};
+
+pub fn process_names() -> Vec<String> {
+    let entries = match fs::read_dir("/proc") {
+        Ok(e) => e,
+        Err(_) => return Vec::new(),
+    };
+
+    entries
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            e.file_name()
+                .to_string_lossy()
+                .bytes()
+                .all(|b| b.is_ascii_digit())
+        })
+        .flat_map(|e| names_from_dir(&e.path()))
+        .collect()
+}
+
+pub fn process_exec_names(process_id: u32) -> Vec<String> {
+    names_from_dir(&Path::new("/proc").join(process_id.to_string()))
+}
+
+fn names_from_dir(dir: &Path) -> Vec<String> {
+    let mut names = Vec::new();
+
+    if let Ok(comm) = fs::read_to_string(dir.join("comm")) {
+        names.push(comm.trim_end().to_string());
+    }
+
+    // Wine and Proton run Windows executables through a loader, so the
+    // executable name is only visible as a Windows path in the first argument
+    if let Ok(cmdline) = fs::read(dir.join("cmdline")) {
+        if let Some(arg) = cmdline.split(|b| *b == 0).next() {
+            let arg = String::from_utf8_lossy(arg);
+
+            match arg.rsplit(|c| c == '/' || c == '\\').next() {
+                Some(name) if !name.is_empty() => names.push(name.to_string()),
+                _ => (),
+            }
+        }
+    }
+
+    names
+}
@@ -0,0 +1,61 @@
+use std::{mem::size_of, path::PathBuf};
+
+use widestring::Utf16String;
+use windows::Win32::{
+    Foundation::{CloseHandle, MAX_PATH},
+    System::{
+        ProcessStatus::{K32EnumProcesses, K32GetModuleFileNameExW},
+        Threading::{OpenProcess, PROCESS_QUERY_INFORMATION},
+    },
+};
+
+pub fn process_names() -> Vec<String> {
+    let mut process_ids: Vec<u32> = vec![0; 2048];
+    let mut bytes_needed = 0;
+
+    let success = unsafe {
+        K32EnumProcesses(
+            process_ids.as_mut_ptr(),
+            (process_ids.len() * size_of::<u32>()) as u32,
+            &mut bytes_needed,
+        )
+    };
+
+    if !success.as_bool() {
+        return Vec::new();
+    }
+
+    process_ids.truncate(bytes_needed as usize / size_of::<u32>());
+
+    process_ids
+        .into_iter()
+        .filter_map(process_exec_name)
+        .collect()
+}
+
+pub fn process_exec_name(process_id: u32) -> Option<String> {
+    if process_id == 0 {
+        return None;
+    }
+
+    let handle_res = unsafe { OpenProcess(PROCESS_QUERY_INFORMATION, false, process_id) };
+
+    let h = match handle_res {
+        Ok(h) => h,
+        Err(_) => return None,
+    };
+
+    let mut buf: [u16; MAX_PATH as usize] = [0; MAX_PATH as usize];
+
+    unsafe {
+        K32GetModuleFileNameExW(h, None, &mut buf);
+        CloseHandle(h);
+    };
+
+    let mut path_string = Utf16String::from_slice_lossy(&buf).to_string();
+    path_string.retain(|c| c != '\0');
+
+    let path = PathBuf::from(path_string);
+
+    path.file_name().map(|s| s.to_string_lossy().into_owned())
+}
@@ -0,0 +1,56 @@
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    api::{ProfileInfoSource, Source},
+    consts::PROFILE_REFRESH_INTERVAL,
+    tray::update_tray,
+    ConfigContainer,
+};
+
+// Keeps the cached names of saved profiles up to date. Profiles that can't
+// be fetched, e.g. while offline, keep their last known names
+pub async fn profile_refresher(handle: AppHandle) {
+    loop {
+        let saved_profiles: Vec<_> = {
+            let container = handle.state::<ConfigContainer>();
+            let lock = container.0.lock().await;
+
+            lock.get_profiles()
+                .saved_profiles
+                .iter()
+                .map(|p| p.profile.clone())
+                .collect()
+        };
+
+        let mut names = Vec::new();
+
+        for profile in saved_profiles {
+            if let Ok(info) = ProfileInfoSource::get_value(profile.clone()).await {
+                names.push((profile, info.display_name, info.display_tag));
+            }
+        }
+
+        let container = handle.state::<ConfigContainer>();
+        let mut lock = container.0.lock().await;
+
+        let mut profiles = lock.get_profiles().clone();
+        let mut changed = false;
+
+        for (profile, name, tag) in names.iter() {
+            changed |= profiles.set_display_name(profile, name, *tag);
+        }
+
+        if changed && lock.set_profiles(profiles).is_ok() {
+            update_tray(&handle, lock.get_profiles());
+
+            if let Some(w) = handle.get_window("profiles") {
+                w.emit("profiles_update", lock.get_profiles().clone())
+                    .unwrap();
+            }
+        }
+
+        drop(lock);
+
+        tokio::time::sleep(PROFILE_REFRESH_INTERVAL).await;
+    }
+}
@@ -0,0 +1,172 @@
+use std::time::{Duration, Instant};
+
+use super::playerdata::PlayerState;
+use crate::{
+    config::preferences::PollingPreferences,
+    consts::{MIN_HISTORY_POLL_RATIO, MIN_POLL_INTERVAL},
+};
+
+pub enum PollKind {
+    Current,
+    History,
+}
+
+pub struct PollScheduler {
+    state: PlayerState,
+    last_change: Instant,
+    activity_ended: Option<Instant>,
+    count: usize,
+}
+
+impl Default for PollScheduler {
+    fn default() -> Self {
+        Self {
+            state: PlayerState::Offline,
+            last_change: Instant::now(),
+            activity_ended: None,
+            count: 0,
+        }
+    }
+}
+
+impl PollScheduler {
+    pub fn next(
+        &mut self,
+        preferences: &PollingPreferences,
+        game_running: bool,
+    ) -> (Duration, PollKind) {
+        let now = Instant::now();
+
+        if let Some(ended) = self.activity_ended {
+            if now.duration_since(ended)
+                > Duration::from_secs(preferences.post_activity_window_secs)
+            {
+                self.activity_ended = None;
+            }
+        }
+
+        self.count += 1;
+
+        // Right after an activity ends, history is polled every other tick
+        // so the clear shows up as soon as the API has it
+        let history_ratio = match self.activity_ended {
+            Some(_) => 2,
+            // Every poll would otherwise be a history poll, and the current
+            // activity would never be polled again
+            None => preferences.history_poll_ratio.max(MIN_HISTORY_POLL_RATIO),
+        };
+
+        let kind = if self.count % history_ratio == 0 {
+            PollKind::History
+        } else {
+            PollKind::Current
+        };
+
+        let interval = if self.activity_ended.is_some() {
+            preferences.post_activity_interval_secs
+        } else if self.state == PlayerState::Offline || !game_running {
+            preferences.offline_interval_secs
+        } else if now.duration_since(self.last_change)
+            > Duration::from_secs(preferences.idle_after_secs)
+        {
+            preferences.idle_interval_secs
+        } else {
+            preferences.active_interval_secs
+        };
+
+        // Hand-edited or imported preferences can hold a 0
+        (Duration::from_secs(interval).max(MIN_POLL_INTERVAL), kind)
+    }
+
+    pub fn record_state(&mut self, state: PlayerState, activity_changed: bool) {
+        if self.state == state && !activity_changed {
+            return;
+        }
+
+        let now = Instant::now();
+
+        if self.state == PlayerState::InTrackedActivity {
+            self.activity_ended = Some(now);
+        }
+
+        self.state = state;
+        self.last_change = now;
+    }
+
+    pub fn record_history(&mut self, changed: bool) {
+        if changed {
+            self.activity_ended = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(scheduler: &mut PollScheduler, preferences: &PollingPreferences) -> Vec<bool> {
+        (0..6)
+            .map(|_| matches!(scheduler.next(preferences, true).1, PollKind::History))
+            .collect()
+    }
+
+    #[test]
+    fn history_is_polled_every_nth_tick() {
+        let preferences = PollingPreferences {
+            history_poll_ratio: 3,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            kinds(&mut PollScheduler::default(), &preferences),
+            [false, false, true, false, false, true]
+        );
+    }
+
+    #[test]
+    fn low_history_ratios_still_poll_the_current_activity() {
+        for ratio in [0, 1] {
+            let preferences = PollingPreferences {
+                history_poll_ratio: ratio,
+                ..Default::default()
+            };
+
+            assert!(kinds(&mut PollScheduler::default(), &preferences).contains(&false));
+        }
+    }
+
+    #[test]
+    fn intervals_follow_the_player_state() {
+        let preferences = PollingPreferences::default();
+        let mut scheduler = PollScheduler::default();
+
+        let interval = |s: &mut PollScheduler, running| s.next(&preferences, running).0;
+
+        assert_eq!(interval(&mut scheduler, true), Duration::from_secs(30));
+
+        scheduler.record_state(PlayerState::InTrackedActivity, true);
+        assert_eq!(interval(&mut scheduler, true), Duration::from_secs(2));
+        assert_eq!(interval(&mut scheduler, false), Duration::from_secs(30));
+
+        scheduler.record_state(PlayerState::Orbit, true);
+        assert_eq!(interval(&mut scheduler, true), Duration::from_secs(1));
+
+        scheduler.record_history(true);
+        assert_eq!(interval(&mut scheduler, true), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn zero_intervals_are_clamped() {
+        let preferences = PollingPreferences {
+            active_interval_secs: 0,
+            offline_interval_secs: 0,
+            ..Default::default()
+        };
+        let mut scheduler = PollScheduler::default();
+
+        assert_eq!(scheduler.next(&preferences, true).0, MIN_POLL_INTERVAL);
+
+        scheduler.record_state(PlayerState::Orbit, true);
+        assert_eq!(scheduler.next(&preferences, true).0, MIN_POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,49 @@
+use tauri::Window;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::config::preferences::GameTargets;
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+mod cache;
+#[cfg(target_os = "windows")]
+mod win32;
+#[cfg(target_os = "linux")]
+mod x11;
+
+pub enum PollResult {
+    Open(WindowRect),
+    Closed,
+    Retain,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct WindowRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub trait GameWindowTracker: Send {
+    // Returns None if the widget windows can't be tracked by this backend
+    fn poll(&mut self, widgets: &[Window], targets: &GameTargets) -> Option<PollResult>;
+
+    // Starts listening for foreground window changes. Returns None if the
+    // backend can't deliver them, in which case it has to be polled
+    fn watch(&mut self) -> Option<UnboundedReceiver<()>>;
+}
+
+#[cfg(target_os = "windows")]
+pub fn create_tracker() -> Option<Box<dyn GameWindowTracker>> {
+    Some(Box::new(win32::Win32Tracker::default()))
+}
+
+#[cfg(target_os = "linux")]
+pub fn create_tracker() -> Option<Box<dyn GameWindowTracker>> {
+    x11::X11Tracker::connect().map(|t| Box::new(t) as Box<dyn GameWindowTracker>)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn create_tracker() -> Option<Box<dyn GameWindowTracker>> {
+    None
+}
@@ -0,0 +1,62 @@
+use std::collections::VecDeque;
+
+use crate::consts::WINDOW_CACHE_SIZE;
+
+// Names of recently seen windows, keyed by window handle. Handles can be
+// reused once a window is destroyed, so every entry remembers the process
+// that owned it and is dropped as soon as that no longer matches
+pub struct WindowCache<K> {
+    entries: VecDeque<CachedWindow<K>>,
+}
+
+struct CachedWindow<K> {
+    window: K,
+    process_id: u32,
+    names: Vec<String>,
+}
+
+impl<K: Copy + Eq> WindowCache<K> {
+    pub fn new() -> Self {
+        Self {
+            entries: VecDeque::with_capacity(WINDOW_CACHE_SIZE),
+        }
+    }
+
+    pub fn get_or_insert_with<F>(&mut self, window: K, process_id: u32, f: F) -> &[String]
+    where
+        F: FnOnce() -> Vec<String>,
+    {
+        let position = self.entries.iter().position(|e| e.window == window);
+
+        let index = match position {
+            Some(i) if self.entries[i].process_id == process_id => i,
+            stale => {
+                if let Some(i) = stale {
+                    let process_id = self.entries[i].process_id;
+
+                    // The window's previous process is most likely gone, so
+                    // nothing else it owned is worth keeping around either
+                    self.invalidate_process(process_id);
+                }
+
+                if self.entries.len() >= WINDOW_CACHE_SIZE {
+                    self.entries.pop_front();
+                }
+
+                self.entries.push_back(CachedWindow {
+                    window,
+                    process_id,
+                    names: f(),
+                });
+
+                self.entries.len() - 1
+            }
+        };
+
+        &self.entries[index].names
+    }
+
+    pub fn invalidate_process(&mut self, process_id: u32) {
+        self.entries.retain(|e| e.process_id != process_id);
+    }
+}
@@ -0,0 +1,261 @@
+use std::{cell::RefCell, sync::mpsc as std_mpsc, thread};
+
+use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use tauri::Window;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use widestring::Utf16String;
+use windows::Win32::{
+    Foundation::{HINSTANCE, HWND, LPARAM, RECT, WPARAM},
+    System::Threading::GetCurrentThreadId,
+    UI::{
+        Accessibility::{SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK},
+        Shell::SHQueryUserNotificationState,
+        WindowsAndMessaging::{
+            DispatchMessageW, GetClassNameW, GetForegroundWindow, GetMessageW, GetWindowRect,
+            GetWindowThreadProcessId, PeekMessageW, PostQuitMessage, PostThreadMessageW,
+            TranslateMessage, EVENT_OBJECT_LOCATIONCHANGE, EVENT_SYSTEM_FOREGROUND,
+            EVENT_SYSTEM_MINIMIZEEND, MSG, OBJID_WINDOW, PM_NOREMOVE, WINEVENT_OUTOFCONTEXT,
+            WINEVENT_SKIPOWNPROCESS, WM_APP,
+        },
+    },
+};
+
+use super::{cache::WindowCache, GameWindowTracker, PollResult, WindowRect};
+use crate::{config::preferences::GameTargets, pollers::process::process_exec_name};
+
+// Out of context hooks are called on the thread that registered them, so the
+// sender lives with that thread rather than being passed to the callback
+thread_local! {
+    static HOOK_SENDER: RefCell<Option<UnboundedSender<()>>> = RefCell::new(None);
+}
+
+// Posted to the hook thread with the game's process id, so that only its
+// windows moving or resizing are reported
+const WM_HOOK_PROCESS: u32 = WM_APP + 1;
+
+pub struct Win32Tracker {
+    // Executable name and window class of recently focused windows
+    hwnd_names: WindowCache<isize>,
+    hook_thread: Option<u32>,
+    hooked_process: u32,
+}
+
+impl Default for Win32Tracker {
+    fn default() -> Self {
+        Self {
+            hwnd_names: WindowCache::new(),
+            hook_thread: None,
+            hooked_process: 0,
+        }
+    }
+}
+
+impl GameWindowTracker for Win32Tracker {
+    fn poll(&mut self, widgets: &[Window], targets: &GameTargets) -> Option<PollResult> {
+        let mut widget_hwnds = Vec::with_capacity(widgets.len());
+
+        for widget in widgets {
+            match widget.raw_window_handle() {
+                RawWindowHandle::Win32(h) if !h.hwnd.is_null() => {
+                    widget_hwnds.push(h.hwnd as isize)
+                }
+                _ => return None,
+            }
+        }
+
+        let notification_state = unsafe { SHQueryUserNotificationState() };
+
+        match notification_state {
+            Ok(n) if n.0 == 3 => return Some(PollResult::Closed), // If in DX exclusive fullscreen mode
+            _ => (),
+        }
+
+        let foreground_hwnd = unsafe { GetForegroundWindow() };
+
+        if widget_hwnds.contains(&foreground_hwnd.0) {
+            return Some(PollResult::Retain);
+        }
+
+        if foreground_hwnd.0 == 0 {
+            return Some(PollResult::Closed);
+        }
+
+        let mut process_id = 0;
+
+        unsafe { GetWindowThreadProcessId(foreground_hwnd, Some(&mut process_id)) };
+
+        if process_id == 0 {
+            return Some(PollResult::Closed);
+        }
+
+        let focused_names =
+            self.hwnd_names
+                .get_or_insert_with(foreground_hwnd.0, process_id, || {
+                    get_hwnd_names(foreground_hwnd, process_id)
+                });
+
+        if !focused_names.iter().any(|n| targets.matches(n)) {
+            return Some(PollResult::Closed);
+        }
+
+        self.hook_process(process_id);
+
+        let mut dims = RECT::default();
+
+        unsafe { GetWindowRect(foreground_hwnd, &mut dims) };
+
+        Some(PollResult::Open(WindowRect {
+            x: dims.left,
+            y: dims.top,
+            width: (dims.right - dims.left) as u32,
+            height: (dims.bottom - dims.top) as u32,
+        }))
+    }
+
+    fn watch(&mut self) -> Option<UnboundedReceiver<()>> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (ready_sender, ready_receiver) = std_mpsc::channel();
+
+        thread::Builder::new()
+            .name("foreground-hook".into())
+            .spawn(move || hook_thread(sender, ready_sender))
+            .ok()?;
+
+        self.hook_thread = ready_receiver.recv().ok().flatten();
+        self.hooked_process = 0;
+
+        self.hook_thread.map(|_| receiver)
+    }
+}
+
+impl Win32Tracker {
+    // Location changes are only hooked for the focused game, as hooking
+    // them for every process wakes the tracker for any window on screen
+    fn hook_process(&mut self, process_id: u32) {
+        let thread_id = match self.hook_thread {
+            Some(t) => t,
+            None => return,
+        };
+
+        if self.hooked_process == process_id {
+            return;
+        }
+
+        let posted = unsafe {
+            PostThreadMessageW(
+                thread_id,
+                WM_HOOK_PROCESS,
+                WPARAM(process_id as usize),
+                LPARAM::default(),
+            )
+        };
+
+        if posted.as_bool() {
+            self.hooked_process = process_id;
+        }
+    }
+}
+
+fn hook_thread(sender: UnboundedSender<()>, ready: std_mpsc::Sender<Option<u32>>) {
+    HOOK_SENDER.with(|s| *s.borrow_mut() = Some(sender));
+
+    // Foreground changes are rare, so they're hooked for every process
+    let hooks = [EVENT_SYSTEM_FOREGROUND, EVENT_SYSTEM_MINIMIZEEND]
+        .map(|event| set_hook(event, 0, WINEVENT_SKIPOWNPROCESS));
+
+    let hooked = hooks.iter().all(|h| !h.is_invalid());
+
+    let mut msg = MSG::default();
+
+    // Creates the thread's message queue, so that no process id posted
+    // before the loop starts is lost
+    unsafe { PeekMessageW(&mut msg, HWND::default(), 0, 0, PM_NOREMOVE) };
+
+    let thread_id = hooked.then(|| unsafe { GetCurrentThreadId() });
+    let mut location_hook = HWINEVENTHOOK::default();
+
+    if ready.send(thread_id).is_ok() && hooked {
+        // GetMessageW returns -1 on error and 0 once PostQuitMessage is called
+        while unsafe { GetMessageW(&mut msg, HWND::default(), 0, 0) }.0 > 0 {
+            // Thread messages have no window to be dispatched to
+            if msg.hwnd.0 == 0 && msg.message == WM_HOOK_PROCESS {
+                if !location_hook.is_invalid() {
+                    unsafe { UnhookWinEvent(location_hook) };
+                }
+
+                location_hook = set_hook(EVENT_OBJECT_LOCATIONCHANGE, msg.wParam.0 as u32, 0);
+                continue;
+            }
+
+            unsafe {
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+    }
+
+    for hook in hooks.iter().chain([&location_hook]) {
+        if !hook.is_invalid() {
+            unsafe { UnhookWinEvent(*hook) };
+        }
+    }
+}
+
+fn set_hook(event: u32, process_id: u32, flags: u32) -> HWINEVENTHOOK {
+    unsafe {
+        SetWinEventHook(
+            event,
+            event,
+            HINSTANCE::default(),
+            Some(win_event_proc),
+            process_id,
+            0,
+            WINEVENT_OUTOFCONTEXT | flags,
+        )
+    }
+}
+
+unsafe extern "system" fn win_event_proc(
+    _hook: HWINEVENTHOOK,
+    event: u32,
+    hwnd: HWND,
+    id_object: i32,
+    _id_child: i32,
+    _event_thread: u32,
+    _event_time: u32,
+) {
+    // Location changes are also reported for the game's carets, cursors and
+    // child windows, only its focused window moving or resizing is relevant
+    if event == EVENT_OBJECT_LOCATIONCHANGE
+        && (id_object != OBJID_WINDOW.0 || hwnd != GetForegroundWindow())
+    {
+        return;
+    }
+
+    let sent = HOOK_SENDER.with(|s| match s.borrow().as_ref() {
+        Some(s) => s.send(()).is_ok(),
+        None => false,
+    });
+
+    // The tracker has been dropped, so stop the message loop
+    if !sent {
+        PostQuitMessage(0);
+    }
+}
+
+fn get_hwnd_names(hwnd: HWND, process_id: u32) -> Vec<String> {
+    let mut names = Vec::new();
+
+    if let Some(n) = process_exec_name(process_id) {
+        names.push(n);
+    }
+
+    let mut class_name = [0; 256];
+    let len = unsafe { GetClassNameW(hwnd, &mut class_name) };
+
+    if len > 0 {
+        names.push(Utf16String::from_slice_lossy(&class_name[..len as usize]).to_string());
+    }
+
+    names
+}
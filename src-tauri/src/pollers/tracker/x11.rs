@@ -0,0 +1,224 @@
+use std::thread;
+
+use anyhow::{anyhow, Result};
+use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use tauri::Window;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        xproto::{Atom, AtomEnum, ChangeWindowAttributesAux, ConnectionExt, EventMask},
+        Event,
+    },
+    rust_connection::RustConnection,
+};
+
+use super::{cache::WindowCache, GameWindowTracker, PollResult, WindowRect};
+use crate::{config::preferences::GameTargets, pollers::process::process_exec_names};
+
+pub struct X11Tracker {
+    conn: RustConnection,
+    root: u32,
+    net_active_window: Atom,
+    net_wm_pid: Atom,
+    // Window classes and executable names of recently focused windows
+    window_names: WindowCache<u32>,
+}
+
+impl X11Tracker {
+    pub fn connect() -> Option<Self> {
+        let (conn, screen) = x11rb::connect(None).ok()?;
+        let root = conn.setup().roots.get(screen)?.root;
+
+        let net_active_window = intern_atom(&conn, b"_NET_ACTIVE_WINDOW").ok()?;
+        let net_wm_pid = intern_atom(&conn, b"_NET_WM_PID").ok()?;
+
+        Some(Self {
+            conn,
+            root,
+            net_active_window,
+            net_wm_pid,
+            window_names: WindowCache::new(),
+        })
+    }
+
+    fn window_pid(&self, window: u32) -> Result<u32> {
+        get_u32_property(&self.conn, window, self.net_wm_pid, AtomEnum::CARDINAL)
+    }
+
+    fn window_rect(&self, window: u32) -> Result<WindowRect> {
+        let geometry = self.conn.get_geometry(window)?.reply()?;
+        let position = self
+            .conn
+            .translate_coordinates(window, self.root, 0, 0)?
+            .reply()?;
+
+        Ok(WindowRect {
+            x: position.dst_x as i32,
+            y: position.dst_y as i32,
+            width: geometry.width as u32,
+            height: geometry.height as u32,
+        })
+    }
+}
+
+impl GameWindowTracker for X11Tracker {
+    fn poll(&mut self, widgets: &[Window], targets: &GameTargets) -> Option<PollResult> {
+        let active_window = match active_window(&self.conn, self.root, self.net_active_window) {
+            Ok(w) if w != 0 => w,
+            _ => return Some(PollResult::Closed),
+        };
+
+        // Widgets may be running natively on Wayland, in which case they
+        // can't become the active X11 window anyway
+        let widget_active = widgets.iter().any(|w| match w.raw_window_handle() {
+            RawWindowHandle::Xlib(h) => h.window as u32 == active_window,
+            _ => false,
+        });
+
+        if widget_active {
+            return Some(PollResult::Retain);
+        }
+
+        // Windows without _NET_WM_PID are cached under process 0 and only
+        // matched by class
+        let pid = self.window_pid(active_window).unwrap_or(0);
+
+        let conn = &self.conn;
+
+        let is_target = self
+            .window_names
+            .get_or_insert_with(active_window, pid, || {
+                let mut names = window_classes(conn, active_window).unwrap_or_default();
+
+                if pid != 0 {
+                    names.extend(process_exec_names(pid));
+                }
+
+                names
+            })
+            .iter()
+            .any(|n| targets.matches(n));
+
+        if !is_target {
+            return Some(PollResult::Closed);
+        }
+
+        match self.window_rect(active_window) {
+            Ok(rect) => Some(PollResult::Open(rect)),
+            Err(_) => Some(PollResult::Closed),
+        }
+    }
+
+    fn watch(&mut self) -> Option<UnboundedReceiver<()>> {
+        // Events are read on their own connection so waiting for them never
+        // holds up the requests made while polling
+        let (conn, screen) = x11rb::connect(None).ok()?;
+        let root = conn.setup().roots.get(screen)?.root;
+
+        let attributes = ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE);
+        conn.change_window_attributes(root, &attributes)
+            .ok()?
+            .check()
+            .ok()?;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let net_active_window = self.net_active_window;
+
+        thread::Builder::new()
+            .name("x11-events".into())
+            .spawn(move || event_thread(conn, root, net_active_window, sender))
+            .ok()?;
+
+        Some(receiver)
+    }
+}
+
+// Notifies on every change of the active window, and on every move or resize
+// of the currently active window. Exits on the first event after the
+// receiving end has been dropped
+fn event_thread(
+    conn: RustConnection,
+    root: u32,
+    net_active_window: Atom,
+    sender: UnboundedSender<()>,
+) {
+    let mut watched_window = None;
+
+    loop {
+        let event = match conn.wait_for_event() {
+            Ok(e) => e,
+            Err(_) => return,
+        };
+
+        match event {
+            Event::PropertyNotify(e) if e.window == root && e.atom == net_active_window => {
+                if let Some(w) = watched_window.take() {
+                    let attributes =
+                        ChangeWindowAttributesAux::new().event_mask(EventMask::NO_EVENT);
+                    let _ = conn.change_window_attributes(w, &attributes);
+                }
+
+                if let Ok(w) = active_window(&conn, root, net_active_window) {
+                    let attributes =
+                        ChangeWindowAttributesAux::new().event_mask(EventMask::STRUCTURE_NOTIFY);
+
+                    if w != 0 && conn.change_window_attributes(w, &attributes).is_ok() {
+                        watched_window = Some(w);
+                    }
+                }
+
+                let _ = conn.flush();
+            }
+            Event::ConfigureNotify(e) if Some(e.window) == watched_window => (),
+            _ => continue,
+        }
+
+        if sender.send(()).is_err() {
+            return;
+        }
+    }
+}
+
+fn active_window(conn: &RustConnection, root: u32, net_active_window: Atom) -> Result<u32> {
+    get_u32_property(conn, root, net_active_window, AtomEnum::WINDOW)
+}
+
+// WM_CLASS holds the instance and class names as consecutive
+// null-terminated strings
+fn window_classes(conn: &RustConnection, window: u32) -> Result<Vec<String>> {
+    let reply = conn
+        .get_property(
+            false,
+            window,
+            AtomEnum::WM_CLASS,
+            AtomEnum::STRING,
+            0,
+            u32::MAX,
+        )?
+        .reply()?;
+
+    Ok(reply
+        .value
+        .split(|b| *b == 0)
+        .filter(|s| !s.is_empty())
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .collect())
+}
+
+fn get_u32_property(
+    conn: &RustConnection,
+    window: u32,
+    property: Atom,
+    type_: AtomEnum,
+) -> Result<u32> {
+    conn.get_property(false, window, property, type_, 0, 1)?
+        .reply()?
+        .value32()
+        .and_then(|mut v| v.next())
+        .ok_or(anyhow!("Property missing"))
+}
+
+fn intern_atom(conn: &RustConnection, name: &[u8]) -> Result<Atom> {
+    Ok(conn.intern_atom(false, name)?.reply()?.atom)
+}
@@ -0,0 +1,292 @@
+use std::net::{Ipv4Addr, SocketAddr};
+
+use anyhow::{bail, Result};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::{
+        header::{AUTHORIZATION, HOST, ORIGIN},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router, Server,
+};
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime;
+use tokio::sync::{broadcast::error::RecvError, watch, Mutex};
+
+use crate::{
+    config::preferences::LocalApiPreferences,
+    events::{EventBus, PlayerDataEvent},
+    pollers::playerdata::PlayerDataStatus,
+};
+
+// The local HTTP server for stream overlays, restarted whenever its
+// preferences change
+#[derive(Default)]
+pub struct LocalApiServer(Mutex<Option<AppliedServer>>);
+
+// The last preferences applied, kept even if the server couldn't start so
+// the same error isn't reported on every apply
+struct AppliedServer {
+    preferences: LocalApiPreferences,
+    // Dropping this stops the server along with its open sockets
+    _stop: Option<watch::Sender<()>>,
+}
+
+#[derive(Clone)]
+struct ServerState {
+    bus: EventBus,
+    token: String,
+    allowed_origins: Vec<String>,
+    stop: watch::Receiver<()>,
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+// Pushed over WebSocket, named like the events the app's windows receive
+#[derive(Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "camelCase")]
+enum SocketMessage<'a> {
+    PlayerdataUpdate(&'a PlayerDataStatus),
+    PlayerdataEvent(&'a PlayerDataEvent),
+}
+
+impl LocalApiServer {
+    pub async fn apply(&self, preferences: &LocalApiPreferences, bus: &EventBus) -> Result<()> {
+        let mut lock = self.0.lock().await;
+
+        if let Some(applied) = lock.as_ref() {
+            if &applied.preferences == preferences {
+                return Ok(());
+            }
+        }
+
+        // Stops the old server first, as the new one may take its port
+        *lock = None;
+
+        let (stop, result) = match preferences.enabled.then(|| start(preferences, bus)) {
+            Some(Ok(s)) => (Some(s), Ok(())),
+            Some(Err(e)) => (None, Err(e)),
+            None => (None, Ok(())),
+        };
+
+        *lock = Some(AppliedServer {
+            preferences: preferences.clone(),
+            _stop: stop,
+        });
+
+        result
+    }
+}
+
+fn start(preferences: &LocalApiPreferences, bus: &EventBus) -> Result<watch::Sender<()>> {
+    if preferences.token.is_empty() {
+        bail!("An access token is required, set one in Preferences");
+    }
+
+    let (stop_sender, stop) = watch::channel(());
+
+    let state = ServerState {
+        bus: bus.clone(),
+        token: preferences.token.clone(),
+        allowed_origins: preferences.allowed_origins.clone(),
+        stop: stop.clone(),
+    };
+
+    let app = Router::new()
+        .route("/playerdata", get(get_playerdata))
+        .route("/ws", get(open_socket))
+        .with_state(state);
+
+    // Only reachable from this machine
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, preferences.port));
+
+    let server = Server::try_bind(&addr)?
+        .serve(app.into_make_service())
+        .with_graceful_shutdown(stopped(stop));
+
+    async_runtime::spawn(async move {
+        if let Err(e) = server.await {
+            eprintln!("Local API server stopped: {e}");
+        }
+    });
+
+    Ok(stop_sender)
+}
+
+impl ServerState {
+    // Requests must name this machine, so that web pages can't reach the
+    // server through a domain rebound to 127.0.0.1. Browser sources can't
+    // set headers, so the token can also be passed in the query string
+    fn check(&self, headers: &HeaderMap, query: &TokenQuery) -> Result<(), StatusCode> {
+        let host = headers.get(HOST).and_then(|h| h.to_str().ok());
+
+        if !host.map_or(false, is_local_host) {
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        let bearer = headers
+            .get(AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "));
+
+        let authorized = [bearer, query.token.as_deref()]
+            .into_iter()
+            .flatten()
+            .any(|t| tokens_match(t, &self.token));
+
+        if !authorized {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+
+        Ok(())
+    }
+
+    // Any web page can open a WebSocket, unlike other tools they always say
+    // where they're from. Browser sources showing local files send "null"
+    fn origin_allowed(&self, headers: &HeaderMap) -> bool {
+        match headers.get(ORIGIN).map(|h| h.to_str()) {
+            None => true,
+            Some(Ok(origin)) => {
+                origin == "null"
+                    || self
+                        .allowed_origins
+                        .iter()
+                        .any(|o| o.trim_end_matches('/').eq_ignore_ascii_case(origin))
+            }
+            Some(Err(_)) => false,
+        }
+    }
+}
+
+fn is_local_host(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.bytes().all(|b| b.is_ascii_digit()) => name,
+        _ => host,
+    };
+
+    name == "127.0.0.1" || name.eq_ignore_ascii_case("localhost")
+}
+
+// Looks at every byte however early the tokens differ, so the time taken
+// doesn't tell how much of a guess was right
+fn tokens_match(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn get_playerdata(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    Query(query): Query<TokenQuery>,
+) -> Response {
+    if let Err(status) = state.check(&headers, &query) {
+        return status.into_response();
+    }
+
+    Json(state.bus.status()).into_response()
+}
+
+async fn open_socket(
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    Query(query): Query<TokenQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    if let Err(status) = state.check(&headers, &query) {
+        return status.into_response();
+    }
+
+    if !state.origin_allowed(&headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    upgrade.on_upgrade(move |socket| push_updates(socket, state))
+}
+
+// Sends the current player data straight away, then every update and
+// event until the socket or the server is closed
+async fn push_updates(mut socket: WebSocket, mut state: ServerState) {
+    let mut status = state.bus.subscribe_status();
+    let mut events = state.bus.subscribe();
+
+    let current = status.borrow_and_update().clone();
+
+    if send(&mut socket, SocketMessage::PlayerdataUpdate(&current))
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    loop {
+        let res = tokio::select! {
+            changed = status.changed() => match changed {
+                Ok(_) => {
+                    let current = status.borrow_and_update().clone();
+                    send(&mut socket, SocketMessage::PlayerdataUpdate(&current)).await
+                }
+                Err(_) => return,
+            },
+            event = events.recv() => match event {
+                Ok(e) => send(&mut socket, SocketMessage::PlayerdataEvent(&e)).await,
+                Err(RecvError::Lagged(_)) => Ok(()),
+                Err(RecvError::Closed) => return,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                Some(Ok(_)) => Ok(()),
+            },
+            _ = state.stop.changed() => return,
+        };
+
+        if res.is_err() {
+            return;
+        }
+    }
+}
+
+async fn send(socket: &mut WebSocket, message: SocketMessage<'_>) -> Result<()> {
+    let text = serde_json::to_string(&message)?;
+    socket.send(Message::Text(text)).await?;
+
+    Ok(())
+}
+
+async fn stopped(mut stop: watch::Receiver<()>) {
+    // Only ever errors, once the sender is dropped
+    let _ = stop.changed().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_local_hosts_are_accepted() {
+        assert!(is_local_host("127.0.0.1:7263"));
+        assert!(is_local_host("LocalHost:7263"));
+        assert!(is_local_host("localhost"));
+        assert!(!is_local_host("rebound.example.com:7263"));
+        assert!(!is_local_host("127.0.0.1.example.com"));
+    }
+
+    #[test]
+    fn tokens_must_match_exactly() {
+        assert!(tokens_match("abc123", "abc123"));
+        assert!(!tokens_match("abc124", "abc123"));
+        assert!(!tokens_match("abc12", "abc123"));
+        assert!(!tokens_match("", "abc123"));
+    }
+}
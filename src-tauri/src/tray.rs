@@ -0,0 +1,65 @@
+use tauri::{AppHandle, CustomMenuItem, SystemTrayMenu, SystemTrayMenuItem, SystemTraySubmenu};
+
+use crate::{
+    config::profiles::{Profile, Profiles, SavedProfile},
+    consts::{APP_NAME, APP_VER},
+};
+
+const PROFILE_ITEM_PREFIX: &str = "profile:";
+
+pub fn tray_menu(profiles: &Profiles) -> SystemTrayMenu {
+    SystemTrayMenu::new()
+        .add_item(
+            CustomMenuItem::new("version_info", format!("{APP_NAME} v{}", APP_VER)).disabled(),
+        )
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_submenu(SystemTraySubmenu::new("Profiles", profiles_menu(profiles)))
+        .add_item(CustomMenuItem::new("preferences", "Preferences"))
+        .add_item(CustomMenuItem::new("set_profile", "Set profile"))
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new("exit", "Exit"))
+}
+
+// Rebuilds the menu, as the tray can't add or remove items in place
+pub fn update_tray(handle: &AppHandle, profiles: &Profiles) {
+    let _ = handle.tray_handle().set_menu(tray_menu(profiles));
+}
+
+// The saved profile a menu item id refers to
+pub fn profile_from_item_id(profiles: &Profiles, id: &str) -> Option<Profile> {
+    let id = id.strip_prefix(PROFILE_ITEM_PREFIX)?;
+
+    profiles
+        .saved_profiles
+        .iter()
+        .find(|p| item_id(&p.profile)[PROFILE_ITEM_PREFIX.len()..] == *id)
+        .map(|p| p.profile.clone())
+}
+
+fn profiles_menu(profiles: &Profiles) -> SystemTrayMenu {
+    if profiles.saved_profiles.is_empty() {
+        return SystemTrayMenu::new()
+            .add_item(CustomMenuItem::new("no_profiles", "No saved profiles").disabled());
+    }
+
+    // Pinned profiles first, in the same order as the profiles window
+    let mut saved: Vec<&SavedProfile> = profiles.saved_profiles.iter().collect();
+    saved.sort_by(|a, b| b.pinned.cmp(&a.pinned).then(b.last_used.cmp(&a.last_used)));
+
+    saved.into_iter().fold(SystemTrayMenu::new(), |menu, p| {
+        let item = CustomMenuItem::new(item_id(&p.profile), p.label());
+
+        if profiles.selected_profile.as_ref() == Some(&p.profile) {
+            menu.add_item(item.selected())
+        } else {
+            menu.add_item(item)
+        }
+    })
+}
+
+fn item_id(profile: &Profile) -> String {
+    format!(
+        "{PROFILE_ITEM_PREFIX}{}:{}",
+        profile.account_platform, profile.account_id
+    )
+}
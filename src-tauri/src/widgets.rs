@@ -0,0 +1,98 @@
+use tauri::{AppHandle, Manager, Window, WindowBuilder, WindowUrl};
+
+use crate::{
+    config::preferences::{WidgetPreferences, WidgetsPreferences},
+    consts::APP_NAME,
+    paths::webview_data_dir,
+};
+
+// Each widget is drawn in its own transparent window, which the overlay
+// poller keeps positioned over the game
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum WidgetKind {
+    Timer,
+    DailyClears,
+    Fireteam,
+    Notifications,
+}
+
+impl WidgetKind {
+    pub const ALL: [WidgetKind; 4] = [
+        WidgetKind::Timer,
+        WidgetKind::DailyClears,
+        WidgetKind::Fireteam,
+        WidgetKind::Notifications,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WidgetKind::Timer => "widget_timer",
+            WidgetKind::DailyClears => "widget_daily_clears",
+            WidgetKind::Fireteam => "widget_fireteam",
+            WidgetKind::Notifications => "widget_notifications",
+        }
+    }
+
+    fn url(self) -> &'static str {
+        match self {
+            WidgetKind::Timer => "./src/overlay/overlay.html#timer",
+            WidgetKind::DailyClears => "./src/overlay/overlay.html#dailyClears",
+            WidgetKind::Fireteam => "./src/overlay/overlay.html#fireteam",
+            WidgetKind::Notifications => "./src/overlay/overlay.html#notifications",
+        }
+    }
+
+    // Unscaled size of the widget's window, in logical pixels
+    pub fn size(self) -> (f64, f64) {
+        match self {
+            WidgetKind::Timer => (280.0, 56.0),
+            WidgetKind::DailyClears => (280.0, 56.0),
+            WidgetKind::Fireteam => (280.0, 240.0),
+            WidgetKind::Notifications => (420.0, 560.0),
+        }
+    }
+
+    pub fn preferences(self, widgets: &WidgetsPreferences) -> &WidgetPreferences {
+        match self {
+            WidgetKind::Timer => &widgets.timer,
+            WidgetKind::DailyClears => &widgets.daily_clears,
+            WidgetKind::Fireteam => &widgets.fireteam,
+            WidgetKind::Notifications => &widgets.notifications,
+        }
+    }
+}
+
+pub fn widget_windows(handle: &AppHandle) -> Vec<(WidgetKind, Window)> {
+    WidgetKind::ALL
+        .iter()
+        .filter_map(|k| handle.get_window(k.label()).map(|w| (*k, w)))
+        .collect()
+}
+
+pub fn create_widget_window(handle: &AppHandle, kind: WidgetKind) -> Result<Window, tauri::Error> {
+    let (width, height) = kind.size();
+
+    let mut builder = WindowBuilder::new(handle, kind.label(), WindowUrl::App(kind.url().into()))
+        .title(APP_NAME)
+        .transparent(true)
+        .decorations(false)
+        .inner_size(width, height)
+        .resizable(false)
+        .always_on_top(true)
+        .position(0.0, 0.0)
+        .visible(false)
+        .skip_taskbar(true);
+
+    if let Some(dir) = webview_data_dir() {
+        builder = builder.data_directory(dir);
+    }
+
+    let window = builder.build()?;
+
+    window.set_ignore_cursor_events(true)?;
+
+    #[cfg(debug_assertions)]
+    window.open_devtools();
+
+    Ok(window)
+}
@@ -8,3 +8,15 @@ export const ACTIVITY_TYPES = {
     18: "Strike",
     87: "Lost Sector"
 }
+
+export const CHARACTER_CLASSES = {
+    0: "Titan",
+    1: "Hunter",
+    2: "Warlock"
+}
+
+export const CHARACTER_RACES = {
+    0: "Human",
+    1: "Awoken",
+    2: "Exo"
+}
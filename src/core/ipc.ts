@@ -1,5 +1,5 @@
 import { invoke } from "@tauri-apps/api/tauri";
-import type { ActivityInfo, BungieProfile, PlayerDataStatus, Preferences, Profile, ProfileInfo, Profiles } from "./types";
+import type { ActivityInfo, BungieProfile, ImportMode, PlayerDataStatus, Preferences, Profile, ProfileInfo, Profiles, WeeklyChecklist } from "./types";
 
 export function openPreferences(): Promise<void> {
     return invoke("open_preferences");
@@ -17,6 +17,14 @@ export function setPreferences(preferences: Preferences): Promise<void> {
     return invoke("set_preferences", { preferences });
 }
 
+export function getGlobalPreferences(): Promise<Preferences> {
+    return invoke("get_global_preferences");
+}
+
+export function setProfilePreferences(preferences: Preferences): Promise<void> {
+    return invoke("set_profile_preferences", { preferences });
+}
+
 export function getProfiles(): Promise<Profiles> {
     return invoke("get_profiles");
 }
@@ -25,6 +33,14 @@ export function setProfiles(profiles: Profiles): Promise<void> {
     return invoke("set_profiles", { profiles });
 }
 
+export function exportConfig(path: string): Promise<void> {
+    return invoke("export_config", { path });
+}
+
+export function importConfig(path: string, mode: ImportMode): Promise<void> {
+    return invoke("import_config", { path, mode });
+}
+
 export function getProfileInfo(profile: Profile): Promise<ProfileInfo> {
     return invoke("get_profile_info", { profile });
 }
@@ -40,3 +56,7 @@ export function searchProfile(displayName: string, displayNameCode: number): Pro
 export function getPlayerdata(): Promise<PlayerDataStatus | null> {
     return invoke("get_playerdata");
 }
+
+export function getWeeklyChecklist(): Promise<WeeklyChecklist> {
+    return invoke("get_weekly_checklist");
+}
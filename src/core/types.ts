@@ -11,10 +11,28 @@ export type BungieProfile = {
 };
 
 export type Profiles = {
-    savedProfiles: Profile[],
+    savedProfiles: SavedProfile[],
     selectedProfile: Profile,
+    preferenceOverrides: PreferenceOverride[],
 }
 
+// Only the preferences that differ from the global ones
+export type PreferenceOverride = {
+    profile: Profile,
+    preferences: Partial<Preferences>,
+}
+
+// Display name and tag are cached from the API and refreshed in the background
+export type SavedProfile = Profile & {
+    displayName: string,
+    displayTag: number,
+    nickname: string,
+    lastUsed: string,
+    pinned: boolean,
+}
+
+export type ImportMode = "merge" | "replace";
+
 export type Profile = {
     accountPlatform: number;
     accountId: string;
@@ -29,9 +47,67 @@ export type ProfileInfo = {
 
 export type Preferences = {
     enableOverlay: boolean;
-    displayDailyClears: boolean;
-    displayClearNotifications: boolean;
     displayMilliseconds: boolean;
+    dailyClearMilestone: number;
+    polling: PollingPreferences;
+    gameTargets: string[];
+    widgets: WidgetsPreferences;
+    visibility: VisibilityPreferences;
+    localApi: LocalApiPreferences;
+};
+
+// A token is generated when the API is enabled without one
+export type LocalApiPreferences = {
+    enabled: boolean;
+    port: number;
+    token: string;
+    allowedOrigins: string[];
+};
+
+export type VisibilityPreferences = {
+    onlyInTrackedActivities: boolean;
+    hideInOrbit: boolean;
+    hideAfterInactiveSecs: number;
+    showAfterClear: boolean;
+};
+
+export type OverlayAnchor = "topLeft" | "topRight" | "bottomLeft" | "bottomRight" | "aboveMinimap" | "custom";
+
+export type OverlayOffset = {
+    value: number;
+    unit: "px" | "percent";
+};
+
+export type WidgetsPreferences = {
+    timer: WidgetPreferences;
+    dailyClears: WidgetPreferences;
+    fireteam: WidgetPreferences;
+    notifications: WidgetPreferences;
+};
+
+export type WidgetPreferences = {
+    enabled: boolean;
+    anchor: OverlayAnchor;
+    offsetX: OverlayOffset;
+    offsetY: OverlayOffset;
+    scale: number;
+};
+
+export type WidgetAlignment = {
+    horizontalEdge: "left" | "right";
+    verticalEdge: "top" | "bottom";
+    scale: number;
+};
+
+export type PollingPreferences = {
+    activeIntervalSecs: number;
+    idleIntervalSecs: number;
+    offlineIntervalSecs: number;
+    postActivityIntervalSecs: number;
+    idleAfterSecs: number;
+    postActivityWindowSecs: number;
+    historyPollRatio: number;
+    suspendWhenGameClosed: boolean;
 };
 
 export type PlayerDataStatus = {
@@ -43,12 +119,45 @@ export type PlayerData = {
     currentActivity: CurrentActivity;
     activityHistory: CompletedActivity[];
     profileInfo: ProfileInfo;
+    sessions: PlaySession[];
+};
+
+export type PlayerState = "offline" | "orbit" | "inTrackedActivity" | "inUntrackedActivity";
+
+export type PlaySession = {
+    start: string;
+    end: string | null;
+    activities: SessionActivity[];
+};
+
+export type SessionActivity = {
+    startDate: string;
+    activityHash: number;
 };
 
 export type CurrentActivity = {
+    state: PlayerState;
     startDate: string;
     activityHash: number;
     activityInfo: ActivityInfo;
+    characterId: string | null;
+    character: CharacterInfo | null;
+    fireteam: FireteamMember[];
+};
+
+export type FireteamMember = {
+    membershipId: string;
+    displayName: string;
+    emblemHash: number;
+};
+
+export type CharacterInfo = {
+    characterId: string;
+    classType: number;
+    raceType: number;
+    light: number;
+    emblemPath: string;
+    dateLastPlayed: string;
 };
 
 export type ActivityInfo = {
@@ -65,4 +174,46 @@ export type CompletedActivity = {
     activityDurationSeconds: number;
     activityHash: number;
     modes: number[];
+    character: CharacterInfo | null;
+};
+
+export type WeeklyChecklist = {
+    weeklyReset: string;
+    characters: CharacterChecklist[];
+};
+
+export type CharacterChecklist = {
+    characterId: string;
+    character: CharacterInfo | null;
+    entries: ChecklistEntry[];
+};
+
+export type ChecklistEntry = {
+    activityHash: number;
+    activityInfo: ActivityInfo;
+    cleared: boolean;
+    milestoneCompleted: boolean | null;
+};
+
+export type PlayerDataEvent =
+    | ({ type: "stateChanged" } & StateChange)
+    | ({ type: "activityStarted" } & CurrentActivity)
+    | ({ type: "activityChanged" } & CurrentActivity)
+    | ({ type: "activityCompleted" } & CompletedActivity)
+    | ({ type: "activityFailed" } & CompletedActivity)
+    | ({ type: "newPersonalBest" } & PersonalBest)
+    | ({ type: "dailyClearMilestone" } & ClearMilestone);
+
+export type StateChange = {
+    previous: PlayerState;
+    state: PlayerState;
+};
+
+export type PersonalBest = {
+    activity: CompletedActivity;
+    previousBestSeconds: number | null;
+};
+
+export type ClearMilestone = {
+    clears: number;
 };
@@ -2,7 +2,7 @@ import "../core/global.css"
 import "./overlay.css"
 import { appWindow } from "@tauri-apps/api/window";
 import { createPopup as _createPopup, type Popup } from "./popups";
-import type { TauriEvent, Preferences, CurrentActivity, PlayerDataStatus } from "../core/types";
+import type { TauriEvent, Preferences, WidgetAlignment, CurrentActivity, PlayerDataStatus, PlayerDataEvent } from "../core/types";
 import { countClears, determineActivityType, formatMillis, formatTime } from "../core/util";
 import { getPlayerdata, getPreferences } from "../core/ipc";
 
@@ -15,9 +15,17 @@ const timeElem = document.querySelector<HTMLElement>("#time")!;
 const msElem = document.querySelector<HTMLElement>("#ms")!;
 const counterElem = document.querySelector<HTMLElement>("#counter")!;
 const dailyElem = document.querySelector<HTMLElement>("#daily")!;
+const fireteamElem = document.querySelector<HTMLElement>("#fireteam")!;
+const fireteamMembersElem = document.querySelector<HTMLElement>("#fireteam-members")!;
+const popupPanelElem = document.querySelector<HTMLElement>("#popup-panel")!;
+
+// Every widget runs in its own window, which loads this page with the
+// widget's name as the hash
+type WidgetName = "timer" | "dailyClears" | "fireteam" | "notifications";
+
+const widgetName = window.location.hash.slice(1) as WidgetName;
 
 let currentActivity: CurrentActivity;
-let lastRaidId;
 let doneInitialRefresh = false;
 
 let shown = false;
@@ -25,7 +33,19 @@ let prefs: Preferences;
 let timerInterval;
 
 async function init() {
-    appWindow.listen("show", () => {
+    if (widgetName == "notifications") {
+        widgetElem.classList.add("hidden");
+    } else {
+        popupPanelElem.classList.add("hidden");
+    }
+
+    if (widgetName != "dailyClears") {
+        counterElem.classList.add("hidden");
+    }
+
+    appWindow.listen("show", (e: TauriEvent<WidgetAlignment>) => {
+        applyAlignment(e.payload);
+
         if (shown) {
             return;
         }
@@ -52,14 +72,19 @@ async function init() {
 
     appWindow.listen("preferences_update", (p: TauriEvent<Preferences>) => applyPreferences(p.payload));
     appWindow.listen("playerdata_update", (e: TauriEvent<PlayerDataStatus>) => refresh(e.payload));
+    appWindow.listen("playerdata_event", (e: TauriEvent<PlayerDataEvent>) => handleEvent(e.payload));
 }
 
 function createPopup(popup: Popup) {
+    if (widgetName != "notifications") {
+        return;
+    }
+
     _createPopup(popup, shown);
 }
 
 function checkTimerInterval() {
-    if (!prefs || !shown || !determineActivityType(currentActivity?.activityInfo?.activityModes)) {
+    if (widgetName != "timer" || !prefs || !shown || !determineActivityType(currentActivity?.activityInfo?.activityModes)) {
         clearTimeout(timerInterval);
         timerInterval = null;
         timerElem.classList.add("hidden");
@@ -104,19 +129,10 @@ function refresh(playerDataStatus: PlayerDataStatus) {
 
     dailyElem.innerText = String(countClears(playerData.activityHistory));
 
-    let latestRaid = playerData.activityHistory[0];
-
-    if (doneInitialRefresh && latestRaid?.completed && lastRaidId != latestRaid.instanceId && prefs.displayClearNotifications) {
-        const type = determineActivityType(latestRaid.modes);
-
-        if (type) {
-            const typeFormatted = type.charAt(0).toUpperCase() + type.slice(1);
-            createPopup({ title: `${typeFormatted} clear result`, subtext: `API Time: <strong>${latestRaid.activityDuration}</strong>` });
-        }
+    if (widgetName == "fireteam") {
+        refreshFireteam();
     }
 
-    lastRaidId = latestRaid?.instanceId;
-
     if (!doneInitialRefresh) {
         createPopup({ title: `${playerData.profileInfo.displayName}#${playerData.profileInfo.displayTag}`, subtext: "Threepole is active." });
     }
@@ -124,14 +140,48 @@ function refresh(playerDataStatus: PlayerDataStatus) {
     doneInitialRefresh = true;
 }
 
-function applyPreferences(p: Preferences) {
-    prefs = p;
+function refreshFireteam() {
+    const members = currentActivity?.fireteam ?? [];
+
+    fireteamMembersElem.replaceChildren(...members.map((m) => {
+        const memberElem = document.createElement("p");
+        memberElem.innerText = m.displayName;
+        return memberElem;
+    }));
 
-    if (p.displayDailyClears) {
-        counterElem.classList.remove("hidden");
+    if (members.length > 0) {
+        fireteamElem.classList.remove("hidden");
     } else {
-        counterElem.classList.add("hidden");
+        fireteamElem.classList.add("hidden");
     }
+}
+
+function handleEvent(event: PlayerDataEvent) {
+    switch (event.type) {
+        case "activityCompleted": {
+            const type = determineActivityType(event.modes);
+
+            if (type) {
+                const typeFormatted = type.charAt(0).toUpperCase() + type.slice(1);
+                createPopup({ title: `${typeFormatted} clear result`, subtext: `API Time: <strong>${event.activityDuration}</strong>` });
+            }
+            break;
+        }
+        case "newPersonalBest":
+            if (event.previousBestSeconds == null) {
+                createPopup({ title: "First clear", subtext: `Cleared in <strong>${formatTime(event.activity.activityDurationSeconds * 1000)}</strong>` });
+            } else {
+                createPopup({ title: "New personal best", subtext: `Previous best: <strong>${formatTime(event.previousBestSeconds * 1000)}</strong>` });
+            }
+            break;
+        case "dailyClearMilestone":
+            createPopup({ title: `${event.clears} clears today`, subtext: "Keep it up!" });
+            break;
+    }
+}
+
+function applyPreferences(p: Preferences) {
+    prefs = p;
 
     if (p.displayMilliseconds) {
         msElem.classList.remove("hidden");
@@ -145,6 +195,24 @@ function applyPreferences(p: Preferences) {
     checkTimerInterval();
 }
 
+function applyAlignment(alignment: WidgetAlignment) {
+    const elem = widgetName == "notifications" ? popupPanelElem : widgetElem;
+
+    elem.style.left = alignment.horizontalEdge == "left" ? "0" : "auto";
+    elem.style.right = alignment.horizontalEdge == "right" ? "0" : "auto";
+    elem.style.top = alignment.verticalEdge == "top" ? "0" : "auto";
+    elem.style.bottom = alignment.verticalEdge == "bottom" ? "0" : "auto";
+    elem.style.transformOrigin = `${alignment.verticalEdge} ${alignment.horizontalEdge}`;
+    elem.style.transform = `scale(${alignment.scale})`;
+
+    widgetContentElem.style.textAlign = alignment.horizontalEdge;
+
+    // The panel fills the window, so it's sized up to cancel out the scaling
+    popupPanelElem.style.width = `${100 / alignment.scale}vw`;
+    popupPanelElem.style.height = `${100 / alignment.scale}vh`;
+    popupPanelElem.style.justifyContent = alignment.verticalEdge == "top" ? "start" : "end";
+}
+
 function timerTick() {
     let millis = Number(new Date()) - Number(new Date(currentActivity.startDate));
     timeElem.innerHTML = formatTime(millis);